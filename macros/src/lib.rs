@@ -0,0 +1,242 @@
+//! Proc macros for rtt_rs, use them through `rtt_rs::task` and `rtt_rs::main`
+//!
+//! The generated code names the future type of the task with
+//! `type_alias_impl_trait`, so the crate using them must enable:
+//! ```
+//! #![feature(type_alias_impl_trait)]
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, AttributeArgs, FnArg, ItemFn, Lit, Meta, NestedMeta, Pat, ReturnType,
+};
+
+struct Args {
+    pool_size: usize,
+    name: String,
+    stack_size: u32,
+    priority: u8,
+}
+
+fn parse_args(args: AttributeArgs, allowed: &[&str]) -> Result<Args, syn::Error> {
+    let mut ret = Args {
+        pool_size: 1,
+        name: "executor".into(),
+        stack_size: 4096,
+        priority: 10,
+    };
+
+    for arg in args {
+        let nv = match arg {
+            NestedMeta::Meta(Meta::NameValue(nv)) => nv,
+            other => return Err(syn::Error::new(other.span(), "expected `name = value`")),
+        };
+        let key = nv
+            .path
+            .get_ident()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        if !allowed.contains(&key.as_str()) {
+            return Err(syn::Error::new(nv.path.span(), "unknown argument"));
+        }
+
+        match (key.as_str(), &nv.lit) {
+            ("pool_size", Lit::Int(v)) => ret.pool_size = v.base10_parse()?,
+            ("stack_size", Lit::Int(v)) => ret.stack_size = v.base10_parse()?,
+            ("priority", Lit::Int(v)) => ret.priority = v.base10_parse()?,
+            ("name", Lit::Str(v)) => ret.name = v.value(),
+            _ => return Err(syn::Error::new(nv.lit.span(), "invalid value")),
+        }
+    }
+
+    if ret.pool_size < 1 {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "pool_size must be 1 or greater",
+        ));
+    }
+
+    Ok(ret)
+}
+
+fn check_async_fn(f: &ItemFn) -> Result<(), syn::Error> {
+    if f.sig.asyncness.is_none() {
+        return Err(syn::Error::new(
+            f.sig.span(),
+            "task functions must be async",
+        ));
+    }
+    if !f.sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            f.sig.generics.span(),
+            "task functions must not be generic",
+        ));
+    }
+    if let ReturnType::Type(_, ty) = &f.sig.output {
+        return Err(syn::Error::new(
+            ty.span(),
+            "task functions must not return a value",
+        ));
+    }
+    Ok(())
+}
+
+fn task_inner(args: Args, f: ItemFn) -> Result<proc_macro2::TokenStream, syn::Error> {
+    check_async_fn(&f)?;
+
+    let mut arg_names = Vec::new();
+    let mut task_fn = f.clone();
+    for arg in task_fn.sig.inputs.iter_mut() {
+        match arg {
+            FnArg::Typed(t) => match t.pat.as_mut() {
+                Pat::Ident(i) => {
+                    arg_names.push(i.ident.clone());
+                    i.mutability = None;
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        t.pat.span(),
+                        "pattern matching in task arguments is not yet supported",
+                    ))
+                }
+            },
+            FnArg::Receiver(r) => {
+                return Err(syn::Error::new(
+                    r.span(),
+                    "task functions must not have `self`",
+                ))
+            }
+        }
+    }
+
+    /* the user's function becomes `task` inside the generated one */
+    let mut inner = f.clone();
+    inner.sig.ident = format_ident!("task");
+    inner.vis = syn::Visibility::Inherited;
+    inner.attrs.clear();
+
+    let attrs = &f.attrs;
+    let vis = &f.vis;
+    let name = &f.sig.ident;
+    let inputs = &task_fn.sig.inputs;
+    let pool_size = args.pool_size;
+    let task_name = name.to_string();
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #name(#inputs) -> ::rtt_rs::embassy_async::executor::SpawnToken<impl ::core::future::Future + 'static> {
+            use ::rtt_rs::embassy_async::executor::TaskStorage;
+            #inner
+            type F = impl ::core::future::Future + 'static;
+            const NEW_TASK: TaskStorage<F> = TaskStorage::new();
+            static POOL: [TaskStorage<F>; #pool_size] = [NEW_TASK; #pool_size];
+            TaskStorage::spawn_pool_named(&POOL, #task_name, move || task(#(#arg_names,)*))
+        }
+    })
+}
+
+/// Declare an async task
+///
+/// Calling the function returns a `SpawnToken` to pass to `Spawner::spawn`.
+/// `pool_size` is the max number of instances running at the same time, default 1.
+///
+/// # Example
+/// ```
+/// #[rtt_rs::task(pool_size = 4)]
+/// async fn blink(pin: u32) {
+///     /* ..... */
+/// }
+///
+/// spawner.spawn(blink(1)).unwrap();
+/// ```
+#[proc_macro_attribute]
+pub fn task(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let f = parse_macro_input!(item as ItemFn);
+
+    let ret = parse_args(args, &["pool_size"]).and_then(|args| task_inner(args, f));
+    match ret {
+        Ok(t) => t.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn main_inner(args: Args, f: ItemFn) -> Result<proc_macro2::TokenStream, syn::Error> {
+    check_async_fn(&f)?;
+
+    if f.sig.inputs.len() != 1 {
+        return Err(syn::Error::new(
+            f.sig.inputs.span(),
+            "main function must have exactly one argument: the `Spawner`",
+        ));
+    }
+
+    let th_name = &args.name;
+    let stack_size = args.stack_size;
+    let priority = args.priority;
+
+    let mut main_task = f.clone();
+    main_task.sig.ident = format_ident!("__rtt_rs_main_task");
+    main_task.vis = syn::Visibility::Inherited;
+    main_task.attrs.clear();
+    let main_task = task_inner(
+        Args {
+            pool_size: 1,
+            name: String::new(),
+            stack_size,
+            priority,
+        },
+        main_task,
+    )?;
+
+    let attrs = &f.attrs;
+    let vis = &f.vis;
+    let name = &f.sig.ident;
+
+    Ok(quote! {
+        #main_task
+
+        #(#attrs)*
+        #vis fn #name() {
+            ::rtt_rs::embassy_async::executor::Executor::builder()
+                .name(#th_name)
+                .stack_size(#stack_size)
+                .priority(#priority)
+                .start(|spawner| spawner.must_spawn(__rtt_rs_main_task(spawner)))
+                .unwrap();
+        }
+    })
+}
+
+/// Run an async main function on an `Executor` in a dedicated thread
+///
+/// The generated function starts the thread and returns once the main task is spawned.
+/// The thread can be configured by `name`, `stack_size` and `priority`.
+///
+/// # Example
+/// ```
+/// use rtt_rs::embassy_async::executor::Spawner;
+/// use rtt_rs::*;
+///
+/// entry!(main);
+///
+/// #[rtt_rs::main(stack_size = 8192)]
+/// async fn main(spawner: Spawner) {
+///     /* ..... */
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let f = parse_macro_input!(item as ItemFn);
+
+    let ret =
+        parse_args(args, &["name", "stack_size", "priority"]).and_then(|args| main_inner(args, f));
+    match ret {
+        Ok(t) => t.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
@@ -14,6 +14,7 @@ pub enum CVoid {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum RTTError {
     ThreadStartupErr,
+    ThreadControlFailed,
     MutexTakeTimeout,
     SemaphoreTakeTimeout,
     QueueSendTimeout,
@@ -24,6 +25,7 @@ pub enum RTTError {
     DeviceOpenFailed,
     DeviceCloseFailed,
     DeviceReadFailed,
+    DeviceReadTimeout,
     DeviceWriteFailed,
     DeviceTransFailed,
     DeviceConfigFailed,
@@ -37,6 +39,10 @@ pub enum RTTError {
     FileExist,
     FileNotExist,
     FileReNameFailed,
+    FileSeekFailed,
+
+    FrameTooLong,
+    FrameInvalid,
 
     FuncUnDefine,
 }
@@ -149,6 +155,13 @@ extern "C" {
     pub(crate) fn rt_thread_yield() -> isize;
     pub(crate) fn rt_thread_delay(tick: u32) -> isize;
     pub(crate) fn rt_thread_mdelay(tick: i32) -> isize;
+    pub(crate) fn rt_thread_delay_until(tick: *mut u32, inc_tick: u32) -> isize;
+    pub(crate) fn rt_tick_get() -> u32;
+    pub(crate) fn rt_thread_control(th: *const CVoid, cmd: i32, arg: *mut CVoid) -> isize;
+
+    /* For thread local storage, the glue returns `&thread->user_data` */
+    #[cfg(feature = "thread-local")]
+    pub(crate) fn rttbase_thread_user_data(th: *const CVoid) -> *mut usize;
 
     /* For mutex */
     pub(crate) fn rt_mutex_create(name: *const u8, flag: u8) -> *const CVoid;
@@ -175,6 +188,7 @@ extern "C" {
         msg_size: usize,
         tick: i32,
     ) -> isize;
+    pub(crate) fn rt_mq_urgent(handle: *const CVoid, msg: *const CVoid, msg_size: usize) -> isize;
     pub(crate) fn rt_mq_delete(handle: *const CVoid) -> isize;
 
     /* For semaphore */
@@ -223,6 +237,13 @@ extern "C" {
     pub(crate) fn rt_thread_yield() -> isize;
     pub(crate) fn rt_thread_delay(tick: u32) -> isize;
     pub(crate) fn rt_thread_mdelay(tick: i32) -> isize;
+    pub(crate) fn rt_thread_delay_until(tick: *mut u32, inc_tick: u32) -> isize;
+    pub(crate) fn rt_tick_get() -> u32;
+    pub(crate) fn rt_thread_control(th: *const CVoid, cmd: i32, arg: *mut CVoid) -> isize;
+
+    /* For thread local storage, the glue returns `&thread->user_data` */
+    #[cfg(feature = "thread-local")]
+    pub(crate) fn rttbase_thread_user_data(th: *const CVoid) -> *mut usize;
 
     /* For mutex */
     pub(crate) fn rt_mutex_create(name: *const u8, flag: u8) -> *const CVoid;
@@ -249,6 +270,7 @@ extern "C" {
         msg_size: usize,
         tick: i32,
     ) -> isize;
+    pub(crate) fn rt_mq_urgent(handle: *const CVoid, msg: *const CVoid, msg_size: usize) -> isize;
     pub(crate) fn rt_mq_delete(handle: *const CVoid) -> isize;
 
     /* For semaphore */
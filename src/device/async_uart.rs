@@ -0,0 +1,171 @@
+//! UART for async tasks
+//!
+//! The rx_indicate / tx_complete callbacks of the device wake the task
+//! waiting on it, so no thread is blocked while waiting for data.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::device::async_uart::AsyncUart;
+//! use rtt_rs::device::uart::UART;
+//!
+//! #[rtt_rs::task]
+//! async fn echo() {
+//!     let dev = UART::new("uart1").open().unwrap();
+//!     let mut dev = AsyncUart::new(dev).unwrap();
+//!
+//!     let mut buf = [0_u8; 16];
+//!     loop {
+//!         let n = dev.read(&mut buf).await.unwrap();
+//!         dev.write_all(&buf[..n]).await.unwrap();
+//!     }
+//! }
+//! ```
+
+use crate::base::{CVoid, RTTError};
+use crate::device::common::*;
+use crate::device::registry::{self, Slot};
+use crate::device::uart::UART;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+pub struct AsyncUart {
+    uart: UART,
+    slot: &'static Slot,
+}
+
+impl AsyncUart {
+    /// Take over a opened UART
+    ///
+    /// # Note:
+    /// This replaces the rx_indicate and tx_complete callbacks of the device,
+    /// closures set by `UART::set_rx_callback` / `UART::set_tx_done_callback` are still called
+    pub fn new(uart: UART) -> Result<AsyncUart, RTTError> {
+        /* on error the slot is released by the drop of the UART */
+        let slot = uart.slot()?;
+        let ret = AsyncUart { uart, slot };
+        ret.uart.set_rx_indicate(registry::rx_indicate)?;
+        ret.uart.set_tx_complete(registry::tx_complete)?;
+        Ok(ret)
+    }
+
+    /// Read the received bytes into `buf`
+    ///
+    /// Waits until at least one byte is received
+    pub fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> ReadFuture<'a> {
+        ReadFuture { dev: self, buf }
+    }
+
+    /// Fill `buf` with received bytes
+    pub async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), RTTError> {
+        let mut pos = 0;
+        while pos < buf.len() {
+            pos += self.read(&mut buf[pos..]).await?;
+        }
+        Ok(())
+    }
+
+    /// Write all bytes of `buf`
+    ///
+    /// Waits for tx_complete when the device does not accept all bytes at once
+    pub fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> WriteAllFuture<'a> {
+        WriteAllFuture { dev: self, buf }
+    }
+
+    /// Get the UART back, the callbacks stay registered but only call the closures of the UART
+    pub fn into_inner(self) -> UART {
+        self.slot.clear_wakers();
+        self.uart
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> Result<usize, RTTError> {
+        self.uart.read_into(buf)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> usize {
+        rttbase_device_write(
+            self.uart.raw_handle(),
+            0,
+            buf.as_ptr() as *const CVoid,
+            buf.len(),
+        )
+    }
+}
+
+/// Future returned by `AsyncUart::read`
+pub struct ReadFuture<'a> {
+    dev: &'a mut AsyncUart,
+    buf: &'a mut [u8],
+}
+
+impl<'a> Future for ReadFuture<'a> {
+    type Output = Result<usize, RTTError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        /* register first, so data arriving after the read still wakes us */
+        this.dev.slot.set_rx_waker(cx.waker());
+        match this.dev.try_read(this.buf) {
+            Ok(0) => Poll::Pending,
+            ret => Poll::Ready(ret),
+        }
+    }
+}
+
+/// Future returned by `AsyncUart::write_all`
+pub struct WriteAllFuture<'a> {
+    dev: &'a mut AsyncUart,
+    buf: &'a [u8],
+}
+
+impl<'a> Future for WriteAllFuture<'a> {
+    type Output = Result<(), RTTError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        while !this.buf.is_empty() {
+            this.dev.slot.set_tx_waker(cx.waker());
+            let len = this.dev.try_write(this.buf);
+            if len == 0 {
+                return Poll::Pending;
+            }
+            if len > this.buf.len() {
+                return Poll::Ready(Err(RTTError::DeviceWriteFailed));
+            }
+            this.buf = &this.buf[len..];
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+mod io_async {
+    use super::AsyncUart;
+    use crate::base::RTTError;
+    use embedded_io_async::{ErrorType, Read, Write};
+
+    impl ErrorType for AsyncUart {
+        type Error = RTTError;
+    }
+
+    impl Read for AsyncUart {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, RTTError> {
+            AsyncUart::read(self, buf).await
+        }
+    }
+
+    impl Write for AsyncUart {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, RTTError> {
+            AsyncUart::write_all(self, buf).await?;
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), RTTError> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,542 @@
+//! Framed serial protocols
+//!
+//! The codecs split a byte stream into frames:
+//! - `Line`: frames ended by a terminator, e.g. `b"\r\n"`
+//! - `Cobs`: COBS encoded frames ended by `0x00`
+//! - `Slip`: SLIP frames (RFC 1055) delimited by `0xC0`
+//!
+//! A decoder takes one byte at a time, so frames can be decoded from any
+//! chunks of received bytes. A malformed or too long frame is reported once,
+//! then its bytes are dropped until the next delimiter.
+//!
+//! `FrameReader` and `LineReader` read the bytes from a `ByteRead` (e.g. `UART`),
+//! bytes received after a frame stay buffered for the next one.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::device::framing::{write_frame, Cobs, FrameReader, LineReader};
+//! use rtt_rs::device::uart::{Timeout, UART};
+//!
+//! let mut dev = UART::new("uart1").open().unwrap();
+//! write_frame(&mut dev, &Cobs::new(64), &[0x11, 0x00, 0x22]).unwrap();
+//!
+//! let mut frames = FrameReader::new(&mut dev, Cobs::new(64));
+//! let frame = frames.read_frame(Timeout::Millis(100)).unwrap();
+//!
+//! let mut lines = LineReader::new(&mut dev, b"\r\n", 128);
+//! let line = lines.read_line(Timeout::Forever).unwrap();
+//! ```
+
+use crate::base::RTTError;
+use crate::device::uart::{Timeout, UART};
+use crate::thread::Thread;
+use crate::vec::Vec;
+
+/// Size of the rx buffer of `FrameReader`
+const RX_CHUNK: usize = 32;
+
+/// Source of received bytes
+pub trait ByteRead {
+    /// Read at least one byte into `buf`, waiting at most `timeout`
+    fn read_some(&mut self, buf: &mut [u8], timeout: Timeout) -> Result<usize, RTTError>;
+}
+
+/// Sink of bytes to send
+pub trait ByteWrite {
+    /// Write all the bytes of `buf`, fails if they can not all be sent
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), RTTError>;
+}
+
+impl ByteRead for UART {
+    fn read_some(&mut self, buf: &mut [u8], timeout: Timeout) -> Result<usize, RTTError> {
+        self.read_timeout(buf, timeout)
+    }
+}
+
+impl ByteWrite for UART {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), RTTError> {
+        UART::write_all(self, buf)
+    }
+}
+
+impl<T: ByteRead + ?Sized> ByteRead for &mut T {
+    fn read_some(&mut self, buf: &mut [u8], timeout: Timeout) -> Result<usize, RTTError> {
+        (**self).read_some(buf, timeout)
+    }
+}
+
+impl<T: ByteWrite + ?Sized> ByteWrite for &mut T {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), RTTError> {
+        (**self).write_all(buf)
+    }
+}
+
+/// Frame decoder
+pub trait Decoder {
+    /// Take the next byte, returns true when a frame is complete
+    ///
+    /// After an error the bytes are dropped until the next delimiter.
+    fn push(&mut self, b: u8) -> Result<bool, RTTError>;
+
+    /// The last complete frame, valid until the next `push`
+    fn frame(&self) -> &[u8];
+
+    /// Drop the partial frame
+    fn reset(&mut self);
+}
+
+/// Frame encoder
+pub trait Encoder {
+    /// Append the encoded frame of `data` to `out`, including the delimiters
+    fn encode(&self, data: &[u8], out: &mut Vec<u8>);
+}
+
+/// Encode `data` and write it to `dev`
+///
+/// # Note:
+/// Allocates the encoded frame on every call, use `write_frame_with` to reuse a buffer.
+pub fn write_frame<W: ByteWrite, E: Encoder>(
+    dev: &mut W,
+    codec: &E,
+    data: &[u8],
+) -> Result<(), RTTError> {
+    write_frame_with(dev, codec, data, &mut Vec::new())
+}
+
+/// Encode `data` into `out` and write it to `dev`
+///
+/// `out` is cleared first, its capacity is kept for the next frame.
+pub fn write_frame_with<W: ByteWrite, E: Encoder>(
+    dev: &mut W,
+    codec: &E,
+    data: &[u8],
+    out: &mut Vec<u8>,
+) -> Result<(), RTTError> {
+    out.clear();
+    codec.encode(data, out);
+    dev.write_all(out)
+}
+
+/* decoded bytes of the current frame, shared by the decoders */
+struct FrameBuf {
+    data: Vec<u8>,
+    max_len: usize,
+    /* `data` holds a complete frame */
+    done: bool,
+    /* dropping bytes until the next delimiter */
+    discard: bool,
+}
+
+impl FrameBuf {
+    fn new(max_len: usize) -> FrameBuf {
+        FrameBuf {
+            data: Vec::new(),
+            max_len,
+            done: false,
+            discard: false,
+        }
+    }
+
+    /* start a new frame after a complete one */
+    fn next(&mut self) {
+        if self.done {
+            self.done = false;
+            self.data.clear();
+        }
+    }
+
+    fn push(&mut self, b: u8) -> Result<(), RTTError> {
+        if self.data.len() >= self.max_len {
+            self.fail();
+            return Err(RTTError::FrameTooLong);
+        }
+        self.data.push(b);
+        Ok(())
+    }
+
+    fn fail(&mut self) {
+        self.data.clear();
+        self.discard = true;
+    }
+
+    fn reset(&mut self) {
+        self.data.clear();
+        self.done = false;
+        self.discard = false;
+    }
+}
+
+/// Frames ended by a terminator
+pub struct Line {
+    terminator: &'static [u8],
+    buf: FrameBuf,
+}
+
+impl Line {
+    /// `max_len` is the max length of a line without the terminator
+    ///
+    /// # Panics
+    /// Panics if `terminator` is empty
+    pub fn new(terminator: &'static [u8], max_len: usize) -> Line {
+        assert!(!terminator.is_empty(), "empty line terminator");
+        Line {
+            terminator,
+            buf: FrameBuf::new(max_len + terminator.len()),
+        }
+    }
+
+    fn discard(&mut self, b: u8) {
+        let t = self.terminator;
+        /* only keep enough bytes to find the terminator */
+        if self.buf.data.len() >= t.len() {
+            self.buf.data.remove(0);
+        }
+        self.buf.data.push(b);
+        if self.buf.data.ends_with(t) {
+            self.buf.reset();
+        }
+    }
+}
+
+impl Decoder for Line {
+    fn push(&mut self, b: u8) -> Result<bool, RTTError> {
+        self.buf.next();
+        if self.buf.discard {
+            self.discard(b);
+            return Ok(false);
+        }
+
+        let t = self.terminator;
+        let len = self.buf.data.len();
+        if len >= self.buf.max_len {
+            /* the tail may be the start of the terminator */
+            self.buf.data.drain(..len + 1 - t.len());
+            self.buf.discard = true;
+            self.discard(b);
+            return Err(RTTError::FrameTooLong);
+        }
+        self.buf.data.push(b);
+        if self.buf.data.ends_with(t) {
+            self.buf.data.truncate(self.buf.data.len() - t.len());
+            self.buf.done = true;
+        }
+        Ok(self.buf.done)
+    }
+
+    fn frame(&self) -> &[u8] {
+        &self.buf.data
+    }
+
+    fn reset(&mut self) {
+        self.buf.reset();
+    }
+}
+
+impl Encoder for Line {
+    fn encode(&self, data: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(data);
+        out.extend_from_slice(self.terminator);
+    }
+}
+
+/// COBS frames ended by `0x00`
+pub struct Cobs {
+    buf: FrameBuf,
+    /* data bytes left in the current block, 0 if a code byte is next */
+    left: u8,
+    /* a zero is decoded before the next block */
+    zero: bool,
+    /* a code byte was received since the last delimiter */
+    started: bool,
+}
+
+impl Cobs {
+    /// `max_len` is the max length of a decoded frame
+    pub fn new(max_len: usize) -> Cobs {
+        Cobs {
+            buf: FrameBuf::new(max_len),
+            left: 0,
+            zero: false,
+            started: false,
+        }
+    }
+}
+
+impl Decoder for Cobs {
+    fn push(&mut self, b: u8) -> Result<bool, RTTError> {
+        self.buf.next();
+        if b == 0 {
+            let (discard, left, started) = (self.buf.discard, self.left, self.started);
+            self.left = 0;
+            self.zero = false;
+            self.started = false;
+            if discard {
+                self.buf.reset();
+                return Ok(false);
+            }
+            if left != 0 {
+                /* the last block is cut off */
+                self.buf.reset();
+                return Err(RTTError::FrameInvalid);
+            }
+            if !started {
+                /* a delimiter without a frame */
+                return Ok(false);
+            }
+            self.buf.done = true;
+            return Ok(true);
+        }
+        if self.buf.discard {
+            return Ok(false);
+        }
+
+        if self.left == 0 {
+            if self.zero {
+                self.buf.push(0)?;
+            }
+            self.started = true;
+            self.left = b - 1;
+            self.zero = b != 0xFF;
+        } else {
+            self.buf.push(b)?;
+            self.left -= 1;
+        }
+        Ok(false)
+    }
+
+    fn frame(&self) -> &[u8] {
+        &self.buf.data
+    }
+
+    fn reset(&mut self) {
+        self.buf.reset();
+        self.left = 0;
+        self.zero = false;
+        self.started = false;
+    }
+}
+
+impl Encoder for Cobs {
+    fn encode(&self, data: &[u8], out: &mut Vec<u8>) {
+        let mut code_pos = out.len();
+        out.push(0);
+        let mut code = 1_u8;
+        for &b in data {
+            if b != 0 {
+                out.push(b);
+                code += 1;
+            }
+            if b == 0 || code == 0xFF {
+                out[code_pos] = code;
+                code_pos = out.len();
+                out.push(0);
+                code = 1;
+            }
+        }
+        out[code_pos] = code;
+        out.push(0);
+    }
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// SLIP frames delimited by `0xC0`
+pub struct Slip {
+    buf: FrameBuf,
+    escape: bool,
+}
+
+impl Slip {
+    /// `max_len` is the max length of a decoded frame
+    pub fn new(max_len: usize) -> Slip {
+        Slip {
+            buf: FrameBuf::new(max_len),
+            escape: false,
+        }
+    }
+}
+
+impl Decoder for Slip {
+    fn push(&mut self, b: u8) -> Result<bool, RTTError> {
+        self.buf.next();
+        if b == SLIP_END {
+            let escape = self.escape;
+            self.escape = false;
+            if self.buf.discard {
+                self.buf.reset();
+                return Ok(false);
+            }
+            if escape {
+                self.buf.reset();
+                return Err(RTTError::FrameInvalid);
+            }
+            /* the END sent before a frame makes an empty one */
+            if self.buf.data.is_empty() {
+                return Ok(false);
+            }
+            self.buf.done = true;
+            return Ok(true);
+        }
+        if self.buf.discard {
+            return Ok(false);
+        }
+
+        if self.escape {
+            self.escape = false;
+            match b {
+                SLIP_ESC_END => self.buf.push(SLIP_END)?,
+                SLIP_ESC_ESC => self.buf.push(SLIP_ESC)?,
+                _ => {
+                    self.buf.fail();
+                    return Err(RTTError::FrameInvalid);
+                }
+            }
+        } else if b == SLIP_ESC {
+            self.escape = true;
+        } else {
+            self.buf.push(b)?;
+        }
+        Ok(false)
+    }
+
+    fn frame(&self) -> &[u8] {
+        &self.buf.data
+    }
+
+    fn reset(&mut self) {
+        self.buf.reset();
+        self.escape = false;
+    }
+}
+
+impl Encoder for Slip {
+    fn encode(&self, data: &[u8], out: &mut Vec<u8>) {
+        out.push(SLIP_END);
+        for &b in data {
+            match b {
+                SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                _ => out.push(b),
+            }
+        }
+        out.push(SLIP_END);
+    }
+}
+
+/// Read frames decoded by `D` from `R`
+pub struct FrameReader<R: ByteRead, D: Decoder> {
+    dev: R,
+    codec: D,
+    rx: [u8; RX_CHUNK],
+    pos: usize,
+    len: usize,
+}
+
+impl<R: ByteRead, D: Decoder> FrameReader<R, D> {
+    pub fn new(dev: R, codec: D) -> FrameReader<R, D> {
+        FrameReader {
+            dev,
+            codec,
+            rx: [0; RX_CHUNK],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Read the next frame, `timeout` is the limit of the whole frame
+    ///
+    /// # Note:
+    /// A malformed frame returns `RTTError::FrameInvalid` or `RTTError::FrameTooLong`,
+    /// the next call continues with the frame after it.
+    pub fn read_frame(&mut self, timeout: Timeout) -> Result<&[u8], RTTError> {
+        let start = Thread::tick();
+        loop {
+            while self.pos < self.len {
+                let b = self.rx[self.pos];
+                self.pos += 1;
+                if self.codec.push(b)? {
+                    return Ok(self.codec.frame());
+                }
+            }
+            self.len = self.dev.read_some(&mut self.rx, timeout.left(start))?;
+            self.pos = 0;
+        }
+    }
+
+    /// Drop the buffered bytes and the partial frame
+    pub fn clear(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+        self.codec.reset();
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.dev
+    }
+
+    /// Get the reader back, the buffered bytes are dropped
+    pub fn into_inner(self) -> R {
+        self.dev
+    }
+}
+
+/// Read lines from `R`
+pub struct LineReader<R: ByteRead>(FrameReader<R, Line>);
+
+impl<R: ByteRead> LineReader<R> {
+    /// `max_len` is the max length of a line without the terminator
+    pub fn new(dev: R, terminator: &'static [u8], max_len: usize) -> LineReader<R> {
+        LineReader(FrameReader::new(dev, Line::new(terminator, max_len)))
+    }
+
+    /// Read the next line without the terminator, see `FrameReader::read_frame`
+    pub fn read_line(&mut self, timeout: Timeout) -> Result<&[u8], RTTError> {
+        self.0.read_frame(timeout)
+    }
+
+    /// Drop the buffered bytes and the partial line
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.0.get_mut()
+    }
+
+    /// Get the reader back, the buffered bytes are dropped
+    pub fn into_inner(self) -> R {
+        self.0.into_inner()
+    }
+}
+
+/// Use an `embedded_io` reader or writer with the frame readers
+///
+/// # Note:
+/// The timeout is ignored, reads wait as long as the reader does.
+/// The end of the input fails with `RTTError::DeviceReadFailed`.
+#[cfg(feature = "embedded-io")]
+pub struct Io<T>(pub T);
+
+#[cfg(feature = "embedded-io")]
+impl<T: embedded_io::Read> ByteRead for Io<T> {
+    fn read_some(&mut self, buf: &mut [u8], _timeout: Timeout) -> Result<usize, RTTError> {
+        match self.0.read(buf) {
+            /* end of the input, no frame will ever be completed */
+            Ok(0) if !buf.is_empty() => Err(RTTError::DeviceReadFailed),
+            Ok(len) => Ok(len),
+            Err(_) => Err(RTTError::DeviceReadFailed),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T: embedded_io::Write> ByteWrite for Io<T> {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), RTTError> {
+        self.0
+            .write_all(buf)
+            .map_err(|_| RTTError::DeviceWriteFailed)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::base::{CVoid, RTTError};
 use crate::device::common::{rttbase_device_find, RtDevice};
+use crate::embassy_async::blocking::{try_spawn_blocking, SendHandle};
 use crate::vec::Vec;
 
 extern "C" {
@@ -85,4 +86,210 @@ impl I2CDevice {
         }
         Ok(())
     }
+
+    /// `read_ack` on a worker thread
+    pub async fn read_ack_async(&self, len: usize, ignore_ack: bool) -> Result<Vec<u8>, RTTError> {
+        let dev = self.send_copy();
+        try_spawn_blocking(move || dev.get().read_ack(len, ignore_ack))?.await
+    }
+
+    /// `write` on a worker thread
+    pub async fn write_async(&self, buf: Vec<u8>, ignore_ack: bool) -> Result<(), RTTError> {
+        let dev = self.send_copy();
+        try_spawn_blocking(move || dev.get().write(buf, ignore_ack))?.await
+    }
+
+    fn send_copy(&self) -> SendI2C {
+        SendI2C {
+            bus: SendHandle(self.bus.raw()),
+            address: self.address,
+            add_10bits: self.add_10bits,
+        }
+    }
+}
+
+/* a copy of a `I2CDevice` moved to a worker thread */
+struct SendI2C {
+    bus: SendHandle,
+    address: u16,
+    add_10bits: bool,
+}
+
+impl SendI2C {
+    fn get(&self) -> I2CDevice {
+        I2CDevice {
+            bus: self.bus.0.into(),
+            address: self.address,
+            add_10bits: self.add_10bits,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+mod hal {
+    use super::{rt_i2c_transfer, I2CDevice, I2CMsg};
+    use crate::base::{CVoid, RTTError};
+    use crate::vec::Vec;
+    use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress, TenBitAddress};
+
+    const RT_I2C_RD: u16 = 1 << 0;
+    const RT_I2C_ADDR_10BIT: u16 = 1 << 2;
+    const RT_I2C_NO_START: u16 = 1 << 4;
+
+    impl ErrorType for I2CDevice {
+        type Error = RTTError;
+    }
+
+    /// The device is used as the bus, the address given to the operations
+    /// replaces the address of the device
+    impl I2c<SevenBitAddress> for I2CDevice {
+        fn transaction(
+            &mut self,
+            address: SevenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), RTTError> {
+            transaction(self, address as u16, 0, operations)
+        }
+    }
+
+    impl I2c<TenBitAddress> for I2CDevice {
+        fn transaction(
+            &mut self,
+            address: TenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), RTTError> {
+            transaction(self, address, RT_I2C_ADDR_10BIT, operations)
+        }
+    }
+
+    pub(super) fn transaction(
+        dev: &I2CDevice,
+        address: u16,
+        flags: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), RTTError> {
+        let mut msgs = Vec::with_capacity(operations.len());
+        let mut last_read = None;
+        for op in operations.iter_mut() {
+            let (read, buf, len) = match op {
+                Operation::Read(buf) => (true, buf.as_ptr(), buf.len()),
+                Operation::Write(buf) => (false, buf.as_ptr(), buf.len()),
+            };
+
+            let mut flag = flags;
+            flag |= if read { RT_I2C_RD } else { 0 };
+            /* adjacent operations of the same kind are merged without a repeated start */
+            flag |= if last_read == Some(read) {
+                RT_I2C_NO_START
+            } else {
+                0
+            };
+            last_read = Some(read);
+
+            msgs.push(I2CMsg {
+                address,
+                flags: flag,
+                len: len as u16,
+                buf,
+            });
+        }
+
+        if msgs.is_empty() {
+            return Ok(());
+        }
+        unsafe {
+            let n = rt_i2c_transfer(
+                dev.bus.raw() as *const CVoid,
+                msgs.as_ptr(),
+                msgs.len() as u32,
+            );
+            if n != msgs.len() {
+                return Err(RTTError::DeviceTransFailed);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The transfers run on the `spawn_blocking` workers with copies of the buffers,
+/// so a cancelled transaction never touches the buffers of the caller
+#[cfg(feature = "embedded-hal-async")]
+mod hal_async {
+    use super::I2CDevice;
+    use crate::base::RTTError;
+    use crate::embassy_async::blocking::{try_spawn_blocking, SendHandle};
+    use crate::vec;
+    use crate::vec::Vec;
+    use embedded_hal::i2c::{Operation, SevenBitAddress, TenBitAddress};
+    use embedded_hal_async::i2c::I2c;
+
+    const RT_I2C_ADDR_10BIT: u16 = 1 << 2;
+
+    impl I2c<SevenBitAddress> for I2CDevice {
+        async fn transaction(
+            &mut self,
+            address: SevenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), RTTError> {
+            transaction(self, address as u16, 0, operations).await
+        }
+    }
+
+    impl I2c<TenBitAddress> for I2CDevice {
+        async fn transaction(
+            &mut self,
+            address: TenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), RTTError> {
+            transaction(self, address, RT_I2C_ADDR_10BIT, operations).await
+        }
+    }
+
+    async fn transaction(
+        dev: &I2CDevice,
+        address: u16,
+        flags: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), RTTError> {
+        /* (read, buffer) */
+        let owned: Vec<(bool, Vec<u8>)> = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buf) => (true, vec![0; buf.len()]),
+                Operation::Write(buf) => (false, buf.to_vec()),
+            })
+            .collect();
+
+        let bus = SendHandle(dev.bus.raw());
+        let add_10bits = dev.add_10bits;
+        let (ret, owned) = try_spawn_blocking(move || {
+            let dev = I2CDevice {
+                bus: bus.0.into(),
+                address,
+                add_10bits,
+            };
+            let mut owned = owned;
+            let mut ops: Vec<Operation<'_>> = owned
+                .iter_mut()
+                .map(|(read, buf)| {
+                    if *read {
+                        Operation::Read(buf)
+                    } else {
+                        Operation::Write(buf)
+                    }
+                })
+                .collect();
+            let ret = super::hal::transaction(&dev, address, flags, &mut ops);
+            drop(ops);
+            (ret, owned)
+        })?
+        .await;
+
+        for (op, (_, buf)) in operations.iter_mut().zip(owned.iter()) {
+            if let Operation::Read(dst) = op {
+                dst.copy_from_slice(buf);
+            }
+        }
+        ret
+    }
 }
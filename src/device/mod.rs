@@ -1,13 +1,16 @@
 //! Device support for rt-thread
 
 pub mod adc;
+pub mod async_uart;
 pub mod can;
 pub mod common;
 pub mod dac;
+pub mod framing;
 pub mod hwtimer;
 pub mod i2c;
 pub mod pin;
 pub mod pwm;
+pub(crate) mod registry;
 pub mod rtc;
 pub mod spi;
 pub mod uart;
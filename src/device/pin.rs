@@ -2,9 +2,17 @@ use crate::alloc::boxed::Box;
 use crate::base::RTTError::DeviceWriteFailed;
 use crate::base::{CVoid, RTBaseError, RTTError};
 use crate::device::common::*;
+use crate::raw_api::no_irq;
+use atomic_polyfill::{AtomicBool, Ordering};
+use core::cell::UnsafeCell;
+use core::future::Future;
 use core::mem;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, Waker};
 
-struct PIN {
+/// Opened with `PIN::new(index).mode(..).open()`, public to be wrapped in `AsyncPin`
+pub struct PIN {
     index: isize,
     mode: Mode,
 }
@@ -15,7 +23,7 @@ struct IRQPin {
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
-enum Mode {
+pub enum Mode {
     Output,
     Input,
     InputPullUp,
@@ -33,12 +41,12 @@ enum IRQMode {
 }
 
 #[derive(Debug)]
-enum PinState {
+pub enum PinState {
     Low,
     High,
 }
 
-struct PinBuilder {
+pub struct PinBuilder {
     index: isize,
     mode: Mode,
 }
@@ -70,14 +78,14 @@ impl Open<PinBuilder> for PIN {
 }
 
 impl PIN {
-    fn new(pin: isize) -> PinBuilder {
+    pub fn new(pin: isize) -> PinBuilder {
         PinBuilder {
             index: pin,
             mode: Mode::Output,
         }
     }
 
-    fn pin_read(&self) -> Result<PinState, RTTError> {
+    pub fn pin_read(&self) -> Result<PinState, RTTError> {
         unsafe {
             Ok(if rt_pin_read(self.index) == 0 {
                 PinState::Low
@@ -162,12 +170,283 @@ impl Drop for IRQPin {
 }
 
 impl PinBuilder {
-    fn mode(&mut self, m: Mode) -> &mut Self {
+    pub fn mode(&mut self, m: Mode) -> &mut Self {
         self.mode = m;
         self
     }
 
-    fn open(&self) -> Result<PIN, RTTError> {
+    pub fn open(&self) -> Result<PIN, RTTError> {
         PIN::open(&self)
     }
 }
+
+/// Input pin for async tasks
+///
+/// The pin interrupt is only enabled while a wait future is pending,
+/// it is disabled again by the interrupt or when the future is dropped.
+///
+/// # Example
+/// ```
+/// use rtt_rs::device::pin::{AsyncPin, Mode, PIN};
+///
+/// #[rtt_rs::task]
+/// async fn button() {
+///     let pin = PIN::new(10).mode(Mode::InputPullUp).open().unwrap();
+///     let mut pin = AsyncPin::new(pin).unwrap();
+///     loop {
+///         pin.wait_for_falling_edge().await;
+///         /* ..... */
+///     }
+/// }
+/// ```
+pub struct AsyncPin {
+    pin: PIN,
+    irq: Box<PinIrq>,
+}
+
+struct PinIrq {
+    index: isize,
+    fired: AtomicBool,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+impl AsyncPin {
+    pub fn new(pin: PIN) -> Result<AsyncPin, RTTError> {
+        if pin.mode == Mode::Output || pin.mode == Mode::OutputOD {
+            return Err(RTTError::DeviceOpenFailed);
+        }
+        let irq = Box::new(PinIrq {
+            index: pin.index,
+            fired: AtomicBool::new(false),
+            waker: UnsafeCell::new(None),
+        });
+        Ok(AsyncPin { pin, irq })
+    }
+
+    pub fn pin_read(&self) -> Result<PinState, RTTError> {
+        self.pin.pin_read()
+    }
+
+    pub fn wait_for_rising_edge(&mut self) -> PinFuture<'_> {
+        PinFuture::new(self, IRQMode::Rising)
+    }
+
+    pub fn wait_for_falling_edge(&mut self) -> PinFuture<'_> {
+        PinFuture::new(self, IRQMode::Falling)
+    }
+
+    pub fn wait_for_any_edge(&mut self) -> PinFuture<'_> {
+        PinFuture::new(self, IRQMode::RisingFalling)
+    }
+
+    /// Ready at the first poll if the pin is already high
+    pub fn wait_for_high(&mut self) -> PinFuture<'_> {
+        PinFuture::new(self, IRQMode::HighLevel)
+    }
+
+    /// Ready at the first poll if the pin is already low
+    pub fn wait_for_low(&mut self) -> PinFuture<'_> {
+        PinFuture::new(self, IRQMode::LowLevel)
+    }
+
+    pub fn into_inner(self) -> PIN {
+        self.disarm();
+        let this = mem::ManuallyDrop::new(self);
+        unsafe {
+            drop(ptr::read(&this.irq));
+            ptr::read(&this.pin)
+        }
+    }
+
+    fn level_reached(&self, mode: u32) -> bool {
+        let level = self.pin.pin_read();
+        if mode == IRQMode::HighLevel as u32 {
+            matches!(level, Ok(PinState::High))
+        } else if mode == IRQMode::LowLevel as u32 {
+            matches!(level, Ok(PinState::Low))
+        } else {
+            false
+        }
+    }
+
+    fn arm(&self, mode: u32) -> Result<(), RTTError> {
+        extern "C" fn f(arg: *mut CVoid) {
+            let irq = unsafe { &*(arg as *const PinIrq) };
+            /* one shot, level interrupts would fire again at once */
+            unsafe {
+                rt_pin_irq_enable(irq.index, 0);
+            }
+            irq.fired.store(true, Ordering::Release);
+            if let Some(w) = no_irq(|| unsafe { (*irq.waker.get()).take() }) {
+                w.wake();
+            }
+        }
+
+        self.irq.fired.store(false, Ordering::Release);
+        let param = &*self.irq as *const PinIrq as *mut CVoid;
+        unsafe {
+            rt_pin_detach_irq(self.pin.index as i32);
+            if 0 != rt_pin_attach_irq(self.pin.index as i32, mode, f, param) {
+                return Err(RTTError::DeviceConfigFailed);
+            }
+            rt_pin_irq_enable(self.pin.index, 1);
+        }
+        Ok(())
+    }
+
+    fn disarm(&self) {
+        unsafe {
+            rt_pin_irq_enable(self.pin.index, 0);
+            rt_pin_detach_irq(self.pin.index as i32);
+        }
+        let old = no_irq(|| unsafe { (*self.irq.waker.get()).take() });
+        drop(old);
+    }
+}
+
+/* a forgotten `PinFuture` leaves the interrupt attached to `irq` */
+impl Drop for AsyncPin {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+/// Future returned by the `AsyncPin` waits
+pub struct PinFuture<'a> {
+    pin: &'a mut AsyncPin,
+    mode: u32,
+    armed: bool,
+    done: bool,
+}
+
+impl<'a> PinFuture<'a> {
+    fn new(pin: &'a mut AsyncPin, mode: IRQMode) -> Self {
+        PinFuture {
+            pin,
+            mode: mode as u32,
+            armed: false,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Future for PinFuture<'a> {
+    type Output = Result<(), RTTError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(Ok(()));
+        }
+        if !this.armed && this.pin.level_reached(this.mode) {
+            this.done = true;
+            return Poll::Ready(Ok(()));
+        }
+
+        let irq = &this.pin.irq;
+        let old = no_irq(|| unsafe { (*irq.waker.get()).replace(cx.waker().clone()) });
+        drop(old);
+
+        if !this.armed {
+            if let Err(e) = this.pin.arm(this.mode) {
+                this.done = true;
+                return Poll::Ready(Err(e));
+            }
+            this.armed = true;
+            return Poll::Pending;
+        }
+
+        if irq.fired.load(Ordering::Acquire) {
+            this.pin.disarm();
+            this.armed = false;
+            this.done = true;
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a> Drop for PinFuture<'a> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.pin.disarm();
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+mod hal {
+    use super::{PinState, PIN};
+    use crate::base::RTTError;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+
+    impl ErrorType for PIN {
+        type Error = RTTError;
+    }
+
+    impl InputPin for PIN {
+        fn is_high(&mut self) -> Result<bool, RTTError> {
+            Ok(matches!(self.pin_read()?, PinState::High))
+        }
+
+        fn is_low(&mut self) -> Result<bool, RTTError> {
+            Ok(matches!(self.pin_read()?, PinState::Low))
+        }
+    }
+
+    impl OutputPin for PIN {
+        fn set_low(&mut self) -> Result<(), RTTError> {
+            self.pin_write(PinState::Low)
+        }
+
+        fn set_high(&mut self) -> Result<(), RTTError> {
+            self.pin_write(PinState::High)
+        }
+    }
+
+    /// The output state is read back from the pin
+    impl StatefulOutputPin for PIN {
+        fn is_set_high(&mut self) -> Result<bool, RTTError> {
+            Ok(matches!(self.pin_read()?, PinState::High))
+        }
+
+        fn is_set_low(&mut self) -> Result<bool, RTTError> {
+            Ok(matches!(self.pin_read()?, PinState::Low))
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+mod hal_async {
+    use super::AsyncPin;
+    use crate::base::RTTError;
+    use embedded_hal::digital::ErrorType;
+    use embedded_hal_async::digital::Wait;
+
+    impl ErrorType for AsyncPin {
+        type Error = RTTError;
+    }
+
+    impl Wait for AsyncPin {
+        async fn wait_for_high(&mut self) -> Result<(), RTTError> {
+            AsyncPin::wait_for_high(self).await
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), RTTError> {
+            AsyncPin::wait_for_low(self).await
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), RTTError> {
+            AsyncPin::wait_for_rising_edge(self).await
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), RTTError> {
+            AsyncPin::wait_for_falling_edge(self).await
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), RTTError> {
+            AsyncPin::wait_for_any_edge(self).await
+        }
+    }
+}
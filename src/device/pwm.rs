@@ -10,27 +10,29 @@ extern "C" {
     fn rt_pwm_disable(dev: *const CVoid, channel: i32) -> RTBaseError;
 }
 
-struct PWM {
+pub struct PWM {
     handle: RtDevice,
 }
 
-struct PWMChannel<'a> {
+pub struct PWMChannel<'a> {
     p: &'a PWM,
     ch: i32,
+    period: u32,
 }
 
 impl PWM {
-    fn new_open(name: &str) -> Result<PWM, RTTError> {
+    pub fn new_open(name: &str) -> Result<PWM, RTTError> {
         let h = rttbase_device_find(name)?;
         Ok(PWM { handle: h })
     }
 
-    fn channel(&self, _channel: i32, period: u32, pulse: u32) -> Result<PWMChannel, RTTError> {
+    pub fn channel(&self, _channel: i32, period: u32, pulse: u32) -> Result<PWMChannel, RTTError> {
         unsafe {
             if 0 == rt_pwm_set(self.handle.raw(), _channel, period, pulse) {
                 Ok(PWMChannel {
                     p: self,
                     ch: _channel,
+                    period,
                 })
             } else {
                 Err(RTTError::DeviceOpenFailed)
@@ -46,7 +48,7 @@ impl<'a> fmt::Display for PWMChannel<'a> {
 }
 
 impl<'a> PWMChannel<'a> {
-    fn enable(&self) -> Result<(), RTTError> {
+    pub fn enable(&self) -> Result<(), RTTError> {
         unsafe {
             if 0 != rt_pwm_enable(self.p.handle.raw(), self.ch) {
                 Err(RTTError::DeviceOpenFailed)
@@ -57,6 +59,23 @@ impl<'a> PWMChannel<'a> {
     }
 }
 
+impl<'a> PWMChannel<'a> {
+    /// Change the pulse width, in ns like the period
+    pub fn set_pulse(&self, pulse: u32) -> Result<(), RTTError> {
+        unsafe {
+            if 0 != rt_pwm_set(self.p.handle.raw(), self.ch, self.period, pulse) {
+                Err(RTTError::DeviceConfigFailed)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+}
+
 impl<'a> Drop for PWMChannel<'a> {
     fn drop(&mut self) {
         unsafe {
@@ -64,3 +83,26 @@ impl<'a> Drop for PWMChannel<'a> {
         }
     }
 }
+
+#[cfg(feature = "embedded-hal")]
+mod hal {
+    use super::PWMChannel;
+    use crate::base::RTTError;
+    use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+    impl<'a> ErrorType for PWMChannel<'a> {
+        type Error = RTTError;
+    }
+
+    /// The duty cycle is scaled to the period of the channel
+    impl<'a> SetDutyCycle for PWMChannel<'a> {
+        fn max_duty_cycle(&self) -> u16 {
+            u16::MAX
+        }
+
+        fn set_duty_cycle(&mut self, duty: u16) -> Result<(), RTTError> {
+            let pulse = self.period as u64 * duty as u64 / u16::MAX as u64;
+            self.set_pulse(pulse as u32)
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Per-device callback registry
+//!
+//! The C callbacks `rx_indicate` / `tx_complete` only get the device handle,
+//! this registry maps the handle to the Rust side state of the device:
+//! the wakers of the waiting tasks, the semaphore of a waiting thread
+//! and the closures set by the user.
+//! Lookups are lock-free, the slots are only changed in a critical section,
+//! so they are safe in interrupt service functions.
+//! Every device handle using a slot holds a reference to it,
+//! the slot is freed when the last handle unregisters.
+
+use crate::base::{CVoid, RTTError};
+#[cfg(not(feature = "smp"))]
+use crate::raw_api::no_irq;
+use crate::semaphore::Semaphore;
+#[cfg(feature = "smp")]
+use crate::smp::SpinLock;
+use crate::Box;
+use atomic_polyfill::{AtomicPtr, Ordering};
+use core::cell::{Cell, UnsafeCell};
+use core::ptr;
+use core::task::Waker;
+
+/// Max number of devices registered at the same time
+const MAX_DEVICES: usize = 8;
+
+#[cfg(feature = "smp")]
+static LOCK: SpinLock<()> = SpinLock::new(());
+
+/* interrupts of this cpu are disabled, with feature `smp` the other cpus are locked out */
+#[cfg(feature = "smp")]
+fn critical<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = LOCK.lock();
+    f()
+}
+
+#[cfg(not(feature = "smp"))]
+fn critical<R>(f: impl FnOnce() -> R) -> R {
+    no_irq(f)
+}
+
+pub(crate) struct Slot {
+    dev: AtomicPtr<CVoid>,
+    /* number of handles using the slot */
+    refs: Cell<usize>,
+    rx_waker: UnsafeCell<Option<Waker>>,
+    tx_waker: UnsafeCell<Option<Waker>>,
+    rx_callback: UnsafeCell<Option<RxCallback>>,
+    tx_callback: UnsafeCell<Option<TxCallback>>,
+    /* released on every rx indication, created by the first blocking read */
+    rx_sem: UnsafeCell<Option<Semaphore>>,
+}
+
+pub(crate) type RxCallback = Box<dyn FnMut(usize) + Send>;
+pub(crate) type TxCallback = Box<dyn FnMut() + Send>;
+
+unsafe impl Sync for Slot {}
+
+impl Slot {
+    const fn new() -> Slot {
+        Slot {
+            dev: AtomicPtr::new(ptr::null_mut()),
+            refs: Cell::new(0),
+            rx_waker: UnsafeCell::new(None),
+            tx_waker: UnsafeCell::new(None),
+            rx_callback: UnsafeCell::new(None),
+            tx_callback: UnsafeCell::new(None),
+            rx_sem: UnsafeCell::new(None),
+        }
+    }
+
+    /* the old closure is dropped outside of the critical section */
+    pub(crate) fn set_rx_callback(&self, f: Option<RxCallback>) {
+        let old = critical(|| unsafe { core::mem::replace(&mut *self.rx_callback.get(), f) });
+        drop(old);
+    }
+
+    /// The semaphore released on every rx indication, created on first use
+    ///
+    /// Valid while the caller holds a reference to the slot,
+    /// it is deleted when the slot is freed.
+    pub(crate) fn rx_sem(&self) -> Result<&Semaphore, RTTError> {
+        if critical(|| unsafe { (*self.rx_sem.get()).is_none() }) {
+            /* created outside of the critical section, the loser of a race is deleted */
+            let mut new = Some(Semaphore::new()?);
+            critical(|| unsafe {
+                let sem = &mut *self.rx_sem.get();
+                if sem.is_none() {
+                    *sem = new.take();
+                }
+            });
+            drop(new);
+        }
+        /* only taken out when the last reference is dropped */
+        Ok(unsafe { (*self.rx_sem.get()).as_ref().unwrap() })
+    }
+
+    pub(crate) fn set_tx_callback(&self, f: Option<TxCallback>) {
+        let old = critical(|| unsafe { core::mem::replace(&mut *self.tx_callback.get(), f) });
+        drop(old);
+    }
+
+    pub(crate) fn set_rx_waker(&self, w: &Waker) {
+        let old = critical(|| unsafe { (*self.rx_waker.get()).replace(w.clone()) });
+        drop(old);
+    }
+
+    pub(crate) fn set_tx_waker(&self, w: &Waker) {
+        let old = critical(|| unsafe { (*self.tx_waker.get()).replace(w.clone()) });
+        drop(old);
+    }
+
+    /// Called in interrupt service functions, the closure is never dropped here
+    ///
+    /// The closure runs in the critical section, it must not change the slot.
+    fn wake_rx(&self, dev: *const CVoid, size: usize) {
+        let waker = critical(|| unsafe {
+            /* the slot may have been freed since it was found */
+            if self.dev.load(Ordering::Acquire) as *const CVoid != dev {
+                return None;
+            }
+            if let Some(f) = &mut *self.rx_callback.get() {
+                f(size);
+            }
+            if let Some(sem) = &*self.rx_sem.get() {
+                sem.release();
+            }
+            (*self.rx_waker.get()).take()
+        });
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+
+    /// Called in interrupt service functions, the closure is never dropped here
+    fn wake_tx(&self, dev: *const CVoid) {
+        let waker = critical(|| unsafe {
+            if self.dev.load(Ordering::Acquire) as *const CVoid != dev {
+                return None;
+            }
+            if let Some(f) = &mut *self.tx_callback.get() {
+                f();
+            }
+            (*self.tx_waker.get()).take()
+        });
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+
+    /// Drop the wakers of the waiting tasks, the closures stay registered
+    pub(crate) fn clear_wakers(&self) {
+        let old = critical(|| unsafe {
+            (
+                (*self.rx_waker.get()).take(),
+                (*self.tx_waker.get()).take(),
+            )
+        });
+        drop(old);
+    }
+}
+
+const EMPTY_SLOT: Slot = Slot::new();
+static SLOTS: [Slot; MAX_DEVICES] = [EMPTY_SLOT; MAX_DEVICES];
+
+/// Take a reference to the slot of `dev`, a free slot is claimed if it has none
+///
+/// Every successful call must be paired with a call of `unregister`.
+pub(crate) fn register(dev: *const CVoid) -> Result<&'static Slot, RTTError> {
+    critical(|| {
+        let slot = match find(dev) {
+            Some(s) => s,
+            None => {
+                let s = SLOTS
+                    .iter()
+                    .find(|s| s.dev.load(Ordering::Acquire).is_null())
+                    .ok_or(RTTError::OutOfMemory)?;
+                s.dev.store(dev as *mut CVoid, Ordering::Release);
+                s
+            }
+        };
+        slot.refs.set(slot.refs.get() + 1);
+        Ok(slot)
+    })
+}
+
+/// Get the slot of `dev`, can be called in interrupt service functions
+pub(crate) fn find(dev: *const CVoid) -> Option<&'static Slot> {
+    SLOTS
+        .iter()
+        .find(|s| s.dev.load(Ordering::Acquire) as *const CVoid == dev)
+}
+
+/// Drop a reference taken by `register`
+///
+/// The last reference frees the slot, pending wakers, closures and the semaphore are dropped.
+pub(crate) fn unregister(dev: *const CVoid) {
+    let old = critical(|| unsafe {
+        let s = find(dev)?;
+        s.refs.set(s.refs.get() - 1);
+        if s.refs.get() != 0 {
+            return None;
+        }
+        s.dev.store(ptr::null_mut(), Ordering::Release);
+        Some((
+            (*s.rx_waker.get()).take(),
+            (*s.tx_waker.get()).take(),
+            (*s.rx_callback.get()).take(),
+            (*s.tx_callback.get()).take(),
+            (*s.rx_sem.get()).take(),
+        ))
+    });
+    drop(old);
+}
+
+/// `rx_indicate` callback waking the task waiting to read `dev`
+pub(crate) extern "C" fn rx_indicate(dev: *const CVoid, size: usize) -> isize {
+    if let Some(s) = find(dev) {
+        s.wake_rx(dev, size);
+    }
+    0
+}
+
+/// `tx_complete` callback waking the task waiting to write `dev`
+pub(crate) extern "C" fn tx_complete(dev: *const CVoid, _buf: *const CVoid) -> isize {
+    if let Some(s) = find(dev) {
+        s.wake_tx(dev);
+    }
+    0
+}
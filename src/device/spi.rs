@@ -41,12 +41,17 @@
 
 use crate::base::*;
 use crate::device::*;
+use crate::embassy_async::blocking::{try_spawn_blocking, SendHandle};
 use crate::vec;
 use crate::vec::*;
 use crate::{min, Box};
 
 extern "C" {
     fn rt_spi_transfer_message(dev: *const CVoid, msg: *mut CVoid) -> usize;
+    fn rt_spi_take_bus(dev: *const CVoid) -> RTBaseError;
+    fn rt_spi_release_bus(dev: *const CVoid) -> RTBaseError;
+    fn rt_spi_take(dev: *const CVoid) -> RTBaseError;
+    fn rt_spi_release(dev: *const CVoid) -> RTBaseError;
 }
 
 pub struct SPI {
@@ -156,6 +161,23 @@ impl SPI {
         self.transfer(msg)?;
         Ok(r_buf[0])
     }
+
+    /// Send `buf` on a worker thread and get the bytes received meanwhile
+    ///
+    /// The async variant of `transfer` with a single message
+    pub async fn transfer_async(&self, buf: Vec<u8>) -> Result<Vec<u8>, RTTError> {
+        let handle = SendHandle(self.handle.raw());
+        try_spawn_blocking(move || {
+            let dev = SPI {
+                handle: handle.0.into(),
+            };
+            let mut rec = Vec::new();
+            rec.resize(buf.len(), 0 as u8);
+            dev.transfer(SpiMsg::new(&buf, &mut rec))?;
+            Ok(rec)
+        })?
+        .await
+    }
 }
 
 pub struct SpiMsg<'a> {
@@ -234,3 +256,269 @@ struct InnerSpiMsg {
     next: *mut InnerSpiMsg,
     flag: u32,
 }
+
+#[cfg(feature = "embedded-hal")]
+mod hal {
+    use super::*;
+    use embedded_hal::spi::{ErrorType, Operation, SpiBus, SpiDevice};
+
+    /* a part of a transfer, `send` or `rec` may be null */
+    struct Segment {
+        send: *const u8,
+        rec: *mut u8,
+        len: usize,
+        /* owns the send buffer of a `TransferInPlace` */
+        copy: Option<Vec<u8>>,
+    }
+
+    impl SPI {
+        /// Transfer the segments without touching the CS line,
+        /// the caller must own the bus
+        fn transfer_segments(&self, segs: &[Segment]) -> Result<(), RTTError> {
+            let mut msgs: Vec<InnerSpiMsg> = segs
+                .iter()
+                .filter(|s| s.len > 0)
+                .map(|s| InnerSpiMsg {
+                    send_buf: s.send as *const CVoid,
+                    rec_buf: s.rec as *mut CVoid,
+                    length: s.len,
+                    next: 0 as _,
+                    flag: 0,
+                })
+                .collect();
+            if msgs.is_empty() {
+                return Ok(());
+            }
+
+            /* the vector is not resized any more, the links stay valid */
+            for i in 1..msgs.len() {
+                msgs[i - 1].next = &mut msgs[i] as *mut InnerSpiMsg;
+            }
+            unsafe {
+                if 0 != rt_spi_transfer_message(self.handle.raw(), msgs.as_mut_ptr() as *mut _) {
+                    Err(RTTError::DeviceTransFailed)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        fn segments(op: &mut Operation<'_, u8>, segs: &mut Vec<Segment>) {
+            let null_send = 0 as *const u8;
+            let null_rec = 0 as *mut u8;
+            match op {
+                Operation::Read(buf) => segs.push(Segment {
+                    send: null_send,
+                    rec: buf.as_mut_ptr(),
+                    len: buf.len(),
+                    copy: None,
+                }),
+                Operation::Write(buf) => segs.push(Segment {
+                    send: buf.as_ptr(),
+                    rec: null_rec,
+                    len: buf.len(),
+                    copy: None,
+                }),
+                Operation::Transfer(read, write) => Self::transfer_pair(read, write, segs),
+                /* the driver may not support the same buffer in both directions */
+                Operation::TransferInPlace(buf) => {
+                    let copy = Vec::from(&buf[..]);
+                    segs.push(Segment {
+                        send: copy.as_ptr(),
+                        rec: buf.as_mut_ptr(),
+                        len: buf.len(),
+                        copy: Some(copy),
+                    })
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+
+        /* buffers of different lengths: the longer one continues alone */
+        fn transfer_pair(read: &mut [u8], write: &[u8], segs: &mut Vec<Segment>) {
+            let n = min(read.len(), write.len());
+            segs.push(Segment {
+                send: write.as_ptr(),
+                rec: read.as_mut_ptr(),
+                len: n,
+                copy: None,
+            });
+            if read.len() > n {
+                segs.push(Segment {
+                    send: 0 as *const u8,
+                    rec: read[n..].as_mut_ptr(),
+                    len: read.len() - n,
+                    copy: None,
+                });
+            }
+            if write.len() > n {
+                segs.push(Segment {
+                    send: write[n..].as_ptr(),
+                    rec: 0 as *mut u8,
+                    len: write.len() - n,
+                    copy: None,
+                });
+            }
+        }
+
+        fn run_operations(&self, operations: &mut [Operation<'_, u8>]) -> Result<(), RTTError> {
+            let mut ret = Ok(());
+            let mut segs = Vec::new();
+            for op in operations.iter_mut() {
+                if let Operation::DelayNs(ns) = op {
+                    ret = ret.and(self.flush_segments(&mut segs));
+                    crate::hal::delay_ns(*ns);
+                } else {
+                    Self::segments(op, &mut segs);
+                }
+            }
+            ret.and(self.flush_segments(&mut segs))
+        }
+
+        fn flush_segments(&self, segs: &mut Vec<Segment>) -> Result<(), RTTError> {
+            let ret = self.transfer_segments(segs);
+            segs.clear();
+            ret
+        }
+    }
+
+    impl ErrorType for SPI {
+        type Error = RTTError;
+    }
+
+    /// CS is asserted for the whole transaction, the bus is locked meanwhile
+    impl SpiDevice for SPI {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), RTTError> {
+            let dev = self.handle.raw();
+            unsafe {
+                if 0 != rt_spi_take_bus(dev) {
+                    return Err(RTTError::DeviceTransFailed);
+                }
+                rt_spi_take(dev);
+            }
+            let ret = self.run_operations(operations);
+            unsafe {
+                rt_spi_release(dev);
+                rt_spi_release_bus(dev);
+            }
+            ret
+        }
+    }
+
+    /// Every call locks the bus, CS is left to the caller
+    impl SpiBus<u8> for SPI {
+        fn read(&mut self, words: &mut [u8]) -> Result<(), RTTError> {
+            self.bus_operation(Operation::Read(words))
+        }
+
+        fn write(&mut self, words: &[u8]) -> Result<(), RTTError> {
+            self.bus_operation(Operation::Write(words))
+        }
+
+        fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), RTTError> {
+            self.bus_operation(Operation::Transfer(read, write))
+        }
+
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), RTTError> {
+            self.bus_operation(Operation::TransferInPlace(words))
+        }
+
+        fn flush(&mut self) -> Result<(), RTTError> {
+            Ok(())
+        }
+    }
+
+    impl SPI {
+        fn bus_operation(&self, op: Operation<'_, u8>) -> Result<(), RTTError> {
+            let dev = self.handle.raw();
+            unsafe {
+                if 0 != rt_spi_take_bus(dev) {
+                    return Err(RTTError::DeviceTransFailed);
+                }
+            }
+            let ret = self.run_operations(&mut [op]);
+            unsafe {
+                rt_spi_release_bus(dev);
+            }
+            ret
+        }
+    }
+}
+
+/// The transfers run on the `spawn_blocking` workers with copies of the buffers,
+/// so a cancelled transaction never touches the buffers of the caller
+#[cfg(feature = "embedded-hal-async")]
+mod hal_async {
+    use super::SPI;
+    use crate::base::RTTError;
+    use crate::embassy_async::blocking::{try_spawn_blocking, SendHandle};
+    use crate::vec;
+    use crate::vec::Vec;
+    use embedded_hal::spi::Operation;
+    use embedded_hal_async::spi::SpiDevice;
+
+    /* owned copy of an `Operation` */
+    enum OwnedOp {
+        Read(Vec<u8>),
+        Write(Vec<u8>),
+        Transfer(Vec<u8>, Vec<u8>),
+        TransferInPlace(Vec<u8>),
+        DelayNs(u32),
+    }
+
+    impl SpiDevice for SPI {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), RTTError> {
+            let owned: Vec<OwnedOp> = operations
+                .iter()
+                .map(|op| match op {
+                    Operation::Read(buf) => OwnedOp::Read(vec![0; buf.len()]),
+                    Operation::Write(buf) => OwnedOp::Write(buf.to_vec()),
+                    Operation::Transfer(read, write) => {
+                        OwnedOp::Transfer(vec![0; read.len()], write.to_vec())
+                    }
+                    Operation::TransferInPlace(buf) => OwnedOp::TransferInPlace(buf.to_vec()),
+                    Operation::DelayNs(ns) => OwnedOp::DelayNs(*ns),
+                })
+                .collect();
+
+            let handle = SendHandle(self.handle.raw());
+            let (ret, owned) = try_spawn_blocking(move || {
+                let mut dev = SPI {
+                    handle: handle.0.into(),
+                };
+                let mut owned = owned;
+                let mut ops: Vec<Operation<'_, u8>> = owned
+                    .iter_mut()
+                    .map(|op| match op {
+                        OwnedOp::Read(buf) => Operation::Read(buf),
+                        OwnedOp::Write(buf) => Operation::Write(buf),
+                        OwnedOp::Transfer(read, write) => Operation::Transfer(read, write),
+                        OwnedOp::TransferInPlace(buf) => Operation::TransferInPlace(buf),
+                        OwnedOp::DelayNs(ns) => Operation::DelayNs(*ns),
+                    })
+                    .collect();
+                let ret = embedded_hal::spi::SpiDevice::transaction(&mut dev, &mut ops);
+                drop(ops);
+                (ret, owned)
+            })?
+            .await;
+
+            for (op, own) in operations.iter_mut().zip(owned.iter()) {
+                match (op, own) {
+                    (Operation::Read(dst), OwnedOp::Read(src)) => dst.copy_from_slice(src),
+                    (Operation::Transfer(dst, _), OwnedOp::Transfer(src, _)) => {
+                        dst.copy_from_slice(src)
+                    }
+                    (Operation::TransferInPlace(dst), OwnedOp::TransferInPlace(src)) => {
+                        dst.copy_from_slice(src)
+                    }
+                    _ => {}
+                }
+            }
+            ret
+        }
+    }
+}
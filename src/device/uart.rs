@@ -2,10 +2,11 @@
 //!
 //! # Examples:
 //! ```
-//! use rtt_rs::device::uart::UART;
+//! use core::fmt::Write;
+//! use rtt_rs::device::uart::{Timeout, UART};
 //! use rtt_rs::device::uart::BaudRate::BaudRate115200;
 //!
-//! let dev = UART::new("uart1")
+//! let mut dev = UART::new("uart1")
 //!     .baud_rate(BaudRate115200)
 //!     .rx_dma(true).open().unwrap();
 //!
@@ -13,17 +14,71 @@
 //!
 //! /* read 10 bytes */
 //! let a = dev.read_bytes(0, 10);
+//!
+//! /* or read into a buffer, and format into the uart */
+//! let mut buf = [0_u8; 10];
+//! let n = dev.read_into(&mut buf).unwrap();
+//! write!(dev, "received {} bytes", n).unwrap();
+//!
+//! /* wait at most 100 ms for 4 bytes */
+//! let mut head = [0_u8; 4];
+//! dev.read_exact(&mut head, Timeout::Millis(100)).unwrap();
 //! ```
 
 use crate::base::{CVoid, RTTError};
 use crate::device::common::*;
+use crate::device::registry::{self, Slot};
+use crate::semaphore::Semaphore;
 use crate::string::String;
+use crate::thread::Thread;
 use crate::vec::*;
+use crate::Box;
 use bitfield::*;
-use core::mem;
+use core::cell::Cell;
+use core::{fmt, mem, slice};
 
 pub struct UART {
     handle: RtDevice,
+    /* byte read ahead by `ReadReady` */
+    pending: Cell<Option<u8>>,
+    /* callback registry slot, registered on first use */
+    slot: Cell<Option<&'static Slot>>,
+}
+
+extern "C" {
+    fn rt_tick_from_millisecond(ms: i32) -> u32;
+}
+
+/// Max waiting time of the blocking reads
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Timeout {
+    NoWait,
+    Ticks(u32),
+    Millis(u32),
+    Forever,
+}
+
+impl Timeout {
+    /* `None` means forever */
+    fn ticks(self) -> Option<u32> {
+        match self {
+            Timeout::NoWait => Some(0),
+            Timeout::Ticks(t) => Some(t),
+            Timeout::Millis(ms) => {
+                let ms = ms.min(i32::MAX as u32) as i32;
+                Some(unsafe { rt_tick_from_millisecond(ms) })
+            }
+            Timeout::Forever => None,
+        }
+    }
+
+    /// Time left of `self` since the tick `start`
+    pub(crate) fn left(self, start: u32) -> Timeout {
+        match self.ticks() {
+            None => Timeout::Forever,
+            Some(t) => Timeout::Ticks(t.saturating_sub(Thread::tick().wrapping_sub(start))),
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -44,6 +99,12 @@ pub enum BaudRate {
     BaudRate2000000 = 2000000,
     BaudRate3000000 = 3000000,
 }
+impl From<BaudRate> for u32 {
+    fn from(b: BaudRate) -> u32 {
+        b as u32
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum DataBits {
     DataBits5 = 5,
@@ -75,30 +136,57 @@ pub enum Invert {
     Normal = 0,
     Inverted = 1,
 }
+#[derive(Copy, Clone)]
+pub enum FlowControl {
+    None = 0,
+    /// RTS/CTS hardware flow control
+    CtsRts = 1,
+}
+
+/// Max ticks `UART::write_all` waits for the device to take more bytes
+pub const WRITE_STALL_TICKS: u32 = 100;
 
 pub struct UARTBuilder {
     name: String,
 
-    baud_rate: BaudRate,
+    baud_rate: u32,
     data_bits: DataBits,
     stop_bits: StopBits,
     parity: Parity,
     bit_order: BitOrder,
     invert: Invert,
-    buf_size: u16,
+    flow_control: FlowControl,
+    buf_size: Option<u16>,
 
     rx_dma: bool,
     tx_dma: bool,
 }
 
+/* `struct serial_configure` */
+#[repr(C)]
 struct UARTBuilderInner {
     baud_rate: u32,
     flag: u32,
 }
 
+/* `struct rt_object_information` */
+#[repr(C)]
+struct ObjectInformation {
+    class: u32,
+    object_list: [*const CVoid; 2],
+    object_size: usize,
+}
+
+const RT_OBJECT_CLASS_DEVICE: u32 = 0x09;
+
+extern "C" {
+    fn rt_object_get_information(class: u32) -> *const ObjectInformation;
+}
+
 impl UARTBuilder {
-    pub fn baud_rate(&mut self, val: BaudRate) -> &mut UARTBuilder {
-        self.baud_rate = val;
+    /// Takes a `BaudRate` or any other rate in bits per second as `u32`
+    pub fn baud_rate<B: Into<u32>>(&mut self, val: B) -> &mut UARTBuilder {
+        self.baud_rate = val.into();
         self
     }
     pub fn data_bits(&mut self, val: DataBits) -> &mut UARTBuilder {
@@ -121,6 +209,19 @@ impl UARTBuilder {
         self.invert = val;
         self
     }
+    pub fn flow_control(&mut self, val: FlowControl) -> &mut UARTBuilder {
+        self.flow_control = val;
+        self
+    }
+    /// Size of the rx buffer of the driver
+    ///
+    /// # Note:
+    /// Only taken when the device is opened, `UART::reconfigure` can not change it.
+    /// Without it the buffer size of the device is kept.
+    pub fn buf_size(&mut self, val: u16) -> &mut UARTBuilder {
+        self.buf_size = Some(val);
+        self
+    }
     pub fn tx_dma(&mut self, f: bool) -> &mut UARTBuilder {
         self.tx_dma = f;
         self
@@ -151,9 +252,24 @@ impl Open<UARTBuilder> for UART {
         } else {
             0x100 /* INT mode on Rx */
         };
-        rttbase_device_open(t.0, open_flag)?;
 
-        let ret = UART { handle: t };
+        let ret = UART {
+            handle: t,
+            pending: Cell::new(None),
+            slot: Cell::new(None),
+        };
+        /* the driver takes the buffer size only while the device is closed */
+        let preset = match builder.buf_size {
+            Some(_) => ret.config(builder),
+            None => Ok(()),
+        };
+        if let Err(e) = preset.and_then(|_| rttbase_device_open(t.0, open_flag)) {
+            /* the device is not open, it must not be closed */
+            mem::forget(ret);
+            return Err(e);
+        }
+
+        /* the hardware is configured only while the device is open */
         ret.config(builder)?;
         Ok(ret)
     }
@@ -162,9 +278,8 @@ impl Open<UARTBuilder> for UART {
 impl UART {
     /// New a uart device
     /// # Note:
-    /// If you want to change the configuration of the UART.
-    ///
-    /// Please close the current UART and reconfigure it.
+    /// The configuration of an opened UART can be changed with `reconfigure`,
+    /// except for the buffer size and the DMA modes.
     ///
     /// # Examples:
     /// ```
@@ -183,13 +298,14 @@ impl UART {
     pub fn new(name: &str) -> UARTBuilder {
         UARTBuilder {
             name: String::from(name),
-            baud_rate: BaudRate::BaudRate115200,
+            baud_rate: BaudRate::BaudRate115200 as u32,
             data_bits: DataBits::DataBits8,
             stop_bits: StopBits::StopBits1,
             parity: Parity::None,
             bit_order: BitOrder::LSB,
             invert: Invert::Normal,
-            buf_size: 64,
+            flow_control: FlowControl::None,
+            buf_size: None,
             /* not use dma default */
             tx_dma: false,
             rx_dma: false,
@@ -198,7 +314,7 @@ impl UART {
 
     fn config(&self, cfg: &UARTBuilder) -> Result<(), RTTError> {
         let mut inner_cfg = UARTBuilderInner {
-            baud_rate: cfg.baud_rate as u32,
+            baud_rate: cfg.baud_rate,
             flag: 0,
         };
 
@@ -210,6 +326,7 @@ impl UART {
             _, bitorder: 8;
             _, invert: 9;
             _, bufsz: 25, 10;
+            _, flowcontrol: 26;
         }
 
         let mut set_flag = Flag(0);
@@ -218,7 +335,12 @@ impl UART {
         set_flag.parity(cfg.parity as u32);
         set_flag.bitorder(cfg.bit_order as u32 != 0);
         set_flag.invert(cfg.invert as u32 != 0);
-        set_flag.bufsz(cfg.buf_size as u32);
+        let buf_size = match cfg.buf_size {
+            Some(s) => s,
+            None => self.device_buf_size(),
+        };
+        set_flag.bufsz(buf_size as u32);
+        set_flag.flowcontrol(cfg.flow_control as u32 != 0);
 
         inner_cfg.flag = set_flag.0;
 
@@ -227,30 +349,147 @@ impl UART {
         Ok(())
     }
 
+    /// The rx buffer size in the configuration of the device
+    ///
+    /// `struct rt_serial_device` starts with the `struct rt_device`,
+    /// the ops pointer and the `struct serial_configure`.
+    fn device_buf_size(&self) -> u16 {
+        unsafe {
+            let info = rt_object_get_information(RT_OBJECT_CLASS_DEVICE);
+            let offset = (*info).object_size + mem::size_of::<*const CVoid>();
+            let cfg = (self.handle.0 as *const u8).add(offset) as *const UARTBuilderInner;
+            /* bufsz: 25, 10 */
+            ((*cfg).flag >> 10) as u16
+        }
+    }
+
+    /// Apply the configuration of `cfg` to the opened device
+    ///
+    /// # Note:
+    /// The name and the DMA modes of `cfg` are ignored. Without a buffer size
+    /// the one of the device is kept, another buffer size fails.
+    ///
+    /// # Examples:
+    /// ```
+    /// use rtt_rs::device::uart::UART;
+    ///
+    /// let dev = UART::new("uart1").open().unwrap();
+    /// dev.reconfigure(UART::new("uart1").baud_rate(250000_u32)).unwrap();
+    /// ```
+    pub fn reconfigure(&self, cfg: &UARTBuilder) -> Result<(), RTTError> {
+        self.config(cfg)
+    }
+
     /// Length information is included in Vec
     pub fn read_bytes(&self, pos: isize, size: usize) -> Result<Vec<u8>, RTTError> {
         let mut buf = Vec::<u8>::new();
         buf.resize(size, 0);
-        let len = rttbase_device_read(self.handle.0, pos, buf.as_mut_ptr() as *mut CVoid, size);
-        if len > size {
+        let len = self.read_at(pos, &mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Read the received bytes into `buf`, returns the number of bytes read
+    ///
+    /// # Note:
+    /// Does not wait, returns 0 if nothing is received
+    pub fn read_into(&self, buf: &mut [u8]) -> Result<usize, RTTError> {
+        self.read_at(0, buf)
+    }
+
+    fn read_at(&self, pos: isize, buf: &mut [u8]) -> Result<usize, RTTError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut head = 0;
+        if let Some(b) = self.pending.take() {
+            buf[0] = b;
+            head = 1;
+        }
+        let rest = &mut buf[head..];
+        let len = rttbase_device_read(
+            self.handle.0,
+            pos,
+            rest.as_mut_ptr() as *mut CVoid,
+            rest.len(),
+        );
+        if len > rest.len() {
+            if head == 1 {
+                self.pending.set(Some(buf[0]));
+            }
             Err(RTTError::DeviceReadFailed)
         } else {
-            unsafe {
-                buf.set_len(len);
+            Ok(head + len)
+        }
+    }
+
+    /// Read the received bytes into `buf`, waits until at least one byte is received
+    ///
+    /// Returns `RTTError::DeviceReadTimeout` if nothing is received within `timeout`.
+    ///
+    /// # Note:
+    /// This replaces the rx_indicate function of the device,
+    /// closures set by `set_rx_callback` are still called
+    pub fn read_timeout(&self, buf: &mut [u8], timeout: Timeout) -> Result<usize, RTTError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let start = Thread::tick();
+        let ticks = timeout.ticks();
+        /* installed before the first read, no byte received after it is missed */
+        let sem = self.rx_sem()?;
+        loop {
+            let len = self.read_into(buf)?;
+            if len != 0 {
+                return Ok(len);
+            }
+            let wait = match ticks {
+                None => -1,
+                Some(t) => match t.checked_sub(Thread::tick().wrapping_sub(start)) {
+                    Some(w) if w > 0 => w.min(i32::MAX as u32) as i32,
+                    _ => return Err(RTTError::DeviceReadTimeout),
+                },
+            };
+            /* the semaphore is released by every indication since it was set,
+             * a timeout only means the next read is the last one */
+            if sem.take(wait).is_err() {
+                return match self.read_into(buf)? {
+                    0 => Err(RTTError::DeviceReadTimeout),
+                    len => Ok(len),
+                };
             }
-            Ok(buf)
         }
     }
 
+    /// Fill `buf` with received bytes, `timeout` is the limit of the whole read
+    ///
+    /// # Note:
+    /// On `RTTError::DeviceReadTimeout` the bytes received so far are dropped
+    pub fn read_exact(&self, buf: &mut [u8], timeout: Timeout) -> Result<(), RTTError> {
+        let start = Thread::tick();
+        let mut pos = 0;
+        while pos < buf.len() {
+            pos += self.read_timeout(&mut buf[pos..], timeout.left(start))?;
+        }
+        Ok(())
+    }
+
+    /* shared by all handles of the device, deleted with the registry slot */
+    fn rx_sem(&self) -> Result<&Semaphore, RTTError> {
+        let sem = self.slot()?.rx_sem()?;
+        self.set_rx_indicate(registry::rx_indicate)?;
+        Ok(sem)
+    }
+
     /// You can receive an object by this function
     ///
     /// # Note:
     /// this function will not deal with big/little endian problem
     pub fn read_obj<T>(&self, pos: isize, obj: &mut T) -> Result<(), RTTError> {
         let size = mem::size_of::<T>();
+        let buf = unsafe { slice::from_raw_parts_mut(obj as *mut T as *mut u8, size) };
 
-        return if size != rttbase_device_read(self.handle.0, pos, obj as *mut T as *mut CVoid, size)
-        {
+        return if size != self.read_at(pos, buf)? {
             Err(RTTError::DeviceReadFailed)
         } else {
             Ok(())
@@ -262,6 +501,30 @@ impl UART {
         rttbase_device_write(self.handle.0, pos, buf.as_ptr() as *const CVoid, buf.len())
     }
 
+    /// Write all bytes of `buf`, waits while the tx fifo of dma mode is full
+    ///
+    /// Fails with `RTTError::DeviceWriteFailed` if nothing is written for `WRITE_STALL_TICKS` ticks
+    pub fn write_all(&self, mut buf: &[u8]) -> Result<(), RTTError> {
+        let mut stalled = 0;
+        while !buf.is_empty() {
+            let len = self.write_bytes(0, buf);
+            if len > buf.len() {
+                return Err(RTTError::DeviceWriteFailed);
+            }
+            if len == 0 {
+                stalled += 1;
+                if stalled > WRITE_STALL_TICKS {
+                    return Err(RTTError::DeviceWriteFailed);
+                }
+                Thread::delay(1);
+            } else {
+                stalled = 0;
+            }
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
     /// You can send an object by this function
     ///
     /// # Note:
@@ -293,12 +556,7 @@ impl UART {
     ///
     /// The registered function will be called in the interrupt service function
     ///
-    /// # Unimportant
-    /// I originally hoped to use closures to achieve this function,
-    /// But the API in RT thread does not support me to pass closures as parameters.
-    /// so I can only use basic functions as the callback function.
-    /// Although we can use a global HashMap to achieve similar functions,
-    /// But this will lose the performance of the system.
+    /// Use `set_rx_callback` to register a closure instead.
     ///
     /// # Note:
     /// It should be noted that this function can only do some simple operations,
@@ -358,6 +616,73 @@ impl UART {
             }
         }
     }
+
+    /// Call `f` with the number of received bytes when data is received
+    ///
+    /// The closure is called in the interrupt service function,
+    /// the same rules as `set_rx_indicate` apply, and it must not set or clear callbacks.
+    /// The closures are shared by all the UART handles of the device,
+    /// they are dropped when the last one is dropped or another closure is set.
+    ///
+    /// # Note:
+    /// This replaces the rx_indicate function of the device
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use rtt_rs::device::uart::UART;
+    /// use rtt_rs::semaphore::Semaphore;
+    /// use rtt_rs::Arc;
+    ///
+    /// let dev = UART::new("uart1").open().unwrap();
+    /// let sem = Arc::new(Semaphore::new().unwrap());
+    /// let s = sem.clone();
+    /// dev.set_rx_callback(move |_size| s.release()).unwrap();
+    ///
+    /// sem.take_wait_forever().unwrap();
+    /// let a = dev.read_bytes(0, 10);
+    /// ```
+    pub fn set_rx_callback<F>(&self, f: F) -> Result<(), RTTError>
+    where
+        F: FnMut(usize) + Send + 'static,
+    {
+        self.slot()?.set_rx_callback(Some(Box::new(f)));
+        self.set_rx_indicate(registry::rx_indicate)
+    }
+
+    /// Call `f` when a transmission is completed
+    ///
+    /// The closure is called in the interrupt service function,
+    /// see `set_rx_callback` for its rules and lifetime.
+    ///
+    /// # Note:
+    /// Only can be use in mode `DMA tx`,
+    /// this replaces the tx_complete function of the device
+    pub fn set_tx_done_callback<F>(&self, f: F) -> Result<(), RTTError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.slot()?.set_tx_callback(Some(Box::new(f)));
+        self.set_tx_complete(registry::tx_complete)
+    }
+
+    /// Drop the closures set by `set_rx_callback` and `set_tx_done_callback`
+    pub fn clear_callbacks(&self) {
+        if let Some(slot) = self.slot.get() {
+            slot.set_rx_callback(None);
+            slot.set_tx_callback(None);
+        }
+    }
+
+    /// The registry slot of the device, shared by all handles of it
+    pub(crate) fn slot(&self) -> Result<&'static Slot, RTTError> {
+        if let Some(s) = self.slot.get() {
+            return Ok(s);
+        }
+        let s = registry::register(self.handle.0)?;
+        self.slot.set(Some(s));
+        Ok(s)
+    }
 }
 
 impl Read<Vec<u8>> for UART {
@@ -366,8 +691,81 @@ impl Read<Vec<u8>> for UART {
     }
 }
 
+impl fmt::Write for UART {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
 impl Drop for UART {
     fn drop(&mut self) {
         rttbase_device_close(self.handle.0).unwrap();
+        if self.slot.get().is_some() {
+            registry::unregister(self.handle.0);
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+mod io {
+    use super::{Timeout, UART, WRITE_STALL_TICKS};
+    use crate::base::RTTError;
+    use crate::thread::Thread;
+    use embedded_io::{ErrorType, Read, ReadReady, Write, WriteReady};
+
+    impl ErrorType for UART {
+        type Error = RTTError;
+    }
+
+    /// Waits with `read_timeout` until at least one byte is received
+    impl Read for UART {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, RTTError> {
+            self.read_timeout(buf, Timeout::Forever)
+        }
+    }
+
+    impl ReadReady for UART {
+        fn read_ready(&mut self) -> Result<bool, RTTError> {
+            if self.pending.get().is_none() {
+                let mut b = [0_u8];
+                if self.read_into(&mut b)? == 1 {
+                    self.pending.set(Some(b[0]));
+                }
+            }
+            Ok(self.pending.get().is_some())
+        }
+    }
+
+    impl Write for UART {
+        /// Fails with `RTTError::DeviceWriteFailed` if nothing is written for `WRITE_STALL_TICKS` ticks
+        fn write(&mut self, buf: &[u8]) -> Result<usize, RTTError> {
+            let mut stalled = 0;
+            loop {
+                let len = self.write_bytes(0, buf);
+                if len > buf.len() {
+                    return Err(RTTError::DeviceWriteFailed);
+                }
+                if len != 0 || buf.is_empty() {
+                    return Ok(len);
+                }
+                stalled += 1;
+                if stalled > WRITE_STALL_TICKS {
+                    return Err(RTTError::DeviceWriteFailed);
+                }
+                Thread::delay(1);
+            }
+        }
+
+        /// The device has no way to wait for the tx fifo, bytes queued in dma mode may still be sending
+        fn flush(&mut self) -> Result<(), RTTError> {
+            Ok(())
+        }
+    }
+
+    /// The device takes bytes at any time, in interrupt mode a write waits until they are sent
+    impl WriteReady for UART {
+        fn write_ready(&mut self) -> Result<bool, RTTError> {
+            Ok(true)
+        }
     }
 }
@@ -0,0 +1,64 @@
+//! Run blocking calls on worker threads
+//!
+//! Device and file calls block the calling thread,
+//! awaited through `spawn_blocking` they block a worker thread instead of the executor.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::embassy_async::blocking::spawn_blocking;
+//! use rtt_rs::fs::File;
+//!
+//! async fn load() -> usize {
+//!     spawn_blocking(|| {
+//!         let f = File::open("/data").unwrap();
+//!         f.read(64).unwrap().len()
+//!     })
+//!     .await
+//! }
+//! ```
+
+use crate::base::{CVoid, RTTError};
+use crate::thread::{JobHandle, ThreadPool};
+use lazy_static::lazy_static;
+
+/// Number of worker threads
+const BLOCKING_THREADS: usize = 2;
+
+lazy_static! {
+    static ref BLOCKING: ThreadPool = ThreadPool::builder()
+        .name("blocking")
+        .threads(BLOCKING_THREADS)
+        .queue_size(16)
+        .stack_size(4096)
+        .build()
+        .unwrap();
+}
+
+/// Run `func` on a worker thread, the returned handle resolves to its result
+///
+/// # Panics
+/// Panics if the job queue of the workers is full, see `try_spawn_blocking`
+pub fn spawn_blocking<F, T>(func: F) -> JobHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    try_spawn_blocking(func).unwrap()
+}
+
+/// Run `func` on a worker thread, the returned handle resolves to its result
+///
+/// Never blocks, fails with `RTTError::QueueSendTimeout` if the job queue of the workers is full
+pub fn try_spawn_blocking<F, T>(func: F) -> Result<JobHandle<T>, RTTError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    BLOCKING.try_submit(func)
+}
+
+/* kernel handles are copied into the jobs, the kernel objects are never freed */
+#[derive(Copy, Clone)]
+pub(crate) struct SendHandle(pub(crate) *const CVoid);
+
+unsafe impl Send for SendHandle {}
@@ -0,0 +1,425 @@
+//! Await rt-thread kernel objects
+//!
+//! A blocking wait on a kernel object would block the executor thread
+//! with all of its tasks. The adapters here first try the object without waiting,
+//! then hand the blocking wait to a small pool of bridge threads
+//! which wake the task when the object becomes available.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::queue::Queue;
+//! use rtt_rs::semaphore::Semaphore;
+//! use rtt_rs::Arc;
+//!
+//! async fn consumer(sem: Arc<Semaphore>, q: Arc<Queue<u32>>) {
+//!     sem.take_async().await;
+//!     let v = q.receive_async().await;
+//!     q.send_async(v + 1).await;
+//! }
+//! ```
+//!
+//! # Note
+//! The futures hold a reference to the kernel object,
+//! it is deleted only after the last future using it is finished or dropped.
+//!
+//! The kernel can not wake a waiting thread on behalf of a dropped future,
+//! so a bridge thread waits at most one slice (10 ticks by default) for a future,
+//! then moves on to the next queued wait. At most as many waits as there are
+//! bridge threads (4 by default) are inside the kernel at a time, the others take turns,
+//! and every pending wait wakes a bridge thread once per slice.
+//! Both are set with `configure`.
+//!
+//! While the wait queue of the bridge threads is full, the tasks are parked
+//! and polled again when a bridge thread takes the next wait.
+//! Nothing here blocks the executor thread, dropping a pending future included.
+
+use atomic_polyfill::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use super::sync::waker::{wake_all, MultiWakerRegistration};
+use crate::base::{CVoid, RTTError};
+use crate::queue::{rttbase_queue_send_wait, Queue};
+use crate::raw_api::no_irq;
+use crate::semaphore::Semaphore;
+use crate::thread::ThreadPool;
+use crate::{Arc, Box};
+use lazy_static::lazy_static;
+
+/// Tasks parked while the wait queue is full, more are woken to poll again
+const PARKED_TASKS: usize = 8;
+
+/// Number of bridge threads
+static BRIDGE_THREADS: AtomicUsize = AtomicUsize::new(4);
+/// Max ticks of a single blocking wait, then the bridge thread takes the next wait
+static BRIDGE_SLICE: AtomicI32 = AtomicI32::new(10);
+static BRIDGE_STARTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref BRIDGE: ThreadPool = {
+        BRIDGE_STARTED.store(true, Ordering::SeqCst);
+        ThreadPool::builder()
+            .name("bridge")
+            .threads(BRIDGE_THREADS.load(Ordering::SeqCst))
+            .queue_size(32)
+            .build()
+            .unwrap()
+    };
+}
+
+/// Set the number of bridge threads and the max ticks of a single wait
+///
+/// More threads let more waits be inside the kernel at a time,
+/// a longer slice costs less CPU time but keeps a thread longer
+/// in the wait of a dropped future.
+///
+/// # Note:
+/// Must be called before the first wait is handed to a bridge thread,
+/// fails with `RTTError::ThreadStartupErr` once the bridge threads are started.
+pub fn configure(threads: usize, slice: u32) -> Result<(), RTTError> {
+    if BRIDGE_STARTED.load(Ordering::SeqCst) {
+        return Err(RTTError::ThreadStartupErr);
+    }
+    BRIDGE_THREADS.store(threads.max(1), Ordering::SeqCst);
+    BRIDGE_SLICE.store(slice.max(1).min(i32::MAX as u32) as i32, Ordering::SeqCst);
+    /* the threads may have been started meanwhile with the old number */
+    if BRIDGE_STARTED.load(Ordering::SeqCst) {
+        return Err(RTTError::ThreadStartupErr);
+    }
+    Ok(())
+}
+
+struct Parked(UnsafeCell<MultiWakerRegistration<PARKED_TASKS>>);
+
+unsafe impl Sync for Parked {}
+
+/* tasks waiting for room in the wait queue */
+static PARKED: Parked = Parked(UnsafeCell::new(MultiWakerRegistration::new()));
+
+fn park(w: &Waker) {
+    let overflow = no_irq(|| unsafe { (*PARKED.0.get()).register(w) });
+    if let Some(wakers) = overflow {
+        wake_all(wakers);
+    }
+}
+
+fn unpark_all() {
+    wake_all(no_irq(|| unsafe { (*PARKED.0.get()).take_all() }));
+}
+
+/// A wait operation on a kernel object
+pub(crate) trait KernelWait: Send + 'static {
+    type Output: Send + 'static;
+
+    /// Wait at most `ticks` ticks, `None` on timeout
+    fn wait(&mut self, ticks: i32) -> Option<Self::Output>;
+
+    /// Undo a successful wait whose result nobody will take, must not block
+    fn give_back(&mut self, val: Self::Output);
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    /* no bridge thread inside a kernel call */
+    Waiting,
+    /* a bridge thread is inside a kernel call */
+    Active,
+    Done,
+    Cancelled,
+}
+
+struct Shared<W: KernelWait> {
+    op: UnsafeCell<W>,
+    state: UnsafeCell<State>,
+    result: UnsafeCell<Option<W::Output>>,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+unsafe impl<W: KernelWait> Send for Shared<W> {}
+unsafe impl<W: KernelWait> Sync for Shared<W> {}
+
+impl<W: KernelWait> Shared<W> {
+    fn submit(self: &Arc<Self>) -> bool {
+        let their = self.clone();
+        BRIDGE
+            .try_execute(move || {
+                /* a wait was taken out of the queue, there is room again */
+                unpark_all();
+                their.run();
+            })
+            .is_ok()
+    }
+
+    /* executed on a bridge thread */
+    fn run(self: Arc<Self>) {
+        loop {
+            let go = no_irq(|| unsafe {
+                if *self.state.get() == State::Waiting {
+                    *self.state.get() = State::Active;
+                    true
+                } else {
+                    false
+                }
+            });
+            if !go {
+                return;
+            }
+
+            /* only this thread touches `op` while the state is Active */
+            let slice = BRIDGE_SLICE.load(Ordering::Relaxed);
+            let ret = unsafe { (*self.op.get()).wait(slice) };
+
+            let (cancelled, ret, waker) = no_irq(|| unsafe {
+                if *self.state.get() == State::Cancelled {
+                    return (true, ret, None);
+                }
+                match ret {
+                    Some(v) => {
+                        *self.result.get() = Some(v);
+                        *self.state.get() = State::Done;
+                        (false, None, (*self.waker.get()).take())
+                    }
+                    None => {
+                        *self.state.get() = State::Waiting;
+                        (false, None, None)
+                    }
+                }
+            });
+
+            if cancelled {
+                if let Some(v) = ret {
+                    unsafe { (*self.op.get()).give_back(v) };
+                }
+                return;
+            }
+            if let Some(w) = waker {
+                w.wake();
+                return;
+            }
+            /* give the other queued waits a turn, keep waiting if there are none */
+            if self.submit() {
+                return;
+            }
+        }
+    }
+}
+
+/// Future of a wait on a kernel object
+///
+/// Holds a reference to the object, so it is not deleted while the wait is pending.
+pub struct KernelFuture<T> {
+    inner: Inner<T>,
+}
+
+enum Inner<T> {
+    /* not handed to a bridge thread yet */
+    Start(Box<dyn PendingWait<T>>),
+    Pending(Box<dyn PendingWait<T>>),
+    Finished,
+}
+
+/* object safe view of `Arc<Shared<_>>` to erase the operation type */
+trait PendingWait<T> {
+    fn try_now(&self) -> Option<T>;
+    fn submit(&self) -> bool;
+    fn poll_result(&self, w: &Waker) -> Option<T>;
+    fn cancel(&self);
+}
+
+impl<W: KernelWait> PendingWait<W::Output> for Arc<Shared<W>> {
+    /* only called before the wait is handed to a bridge thread */
+    fn try_now(&self) -> Option<W::Output> {
+        unsafe { (*self.op.get()).wait(0) }
+    }
+
+    fn submit(&self) -> bool {
+        Shared::submit(self)
+    }
+
+    fn poll_result(&self, w: &Waker) -> Option<W::Output> {
+        let (ret, old) = no_irq(|| unsafe {
+            match (*self.result.get()).take() {
+                Some(v) => (Some(v), None),
+                None => (None, (*self.waker.get()).replace(w.clone())),
+            }
+        });
+        drop(old);
+        ret
+    }
+
+    fn cancel(&self) {
+        let (prev, ret) = no_irq(|| unsafe {
+            let prev = *self.state.get();
+            *self.state.get() = State::Cancelled;
+            (prev, (*self.result.get()).take())
+        });
+
+        /* an Active bridge thread gives its result back itself */
+        if prev == State::Done {
+            if let Some(v) = ret {
+                unsafe { (*self.op.get()).give_back(v) };
+            }
+        }
+    }
+}
+
+impl<T: 'static> KernelFuture<T> {
+    pub(crate) fn new<W: KernelWait<Output = T>>(op: W) -> Self {
+        let shared = Arc::new(Shared {
+            op: UnsafeCell::new(op),
+            state: UnsafeCell::new(State::Waiting),
+            result: UnsafeCell::new(None),
+            waker: UnsafeCell::new(None),
+        });
+        KernelFuture {
+            inner: Inner::Start(Box::new(shared)),
+        }
+    }
+}
+
+impl<T> Unpin for KernelFuture<T> {}
+
+impl<T> Future for KernelFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        match core::mem::replace(&mut this.inner, Inner::Finished) {
+            Inner::Start(p) => {
+                if let Some(v) = p.try_now() {
+                    return Poll::Ready(v);
+                }
+                /* register before the bridge thread can complete the wait */
+                if let Some(v) = p.poll_result(cx.waker()) {
+                    return Poll::Ready(v);
+                }
+                if p.submit() {
+                    this.inner = Inner::Pending(p);
+                    return Poll::Pending;
+                }
+                /* the wait queue is full, retry once parked so no free room is missed */
+                park(cx.waker());
+                this.inner = if p.submit() {
+                    Inner::Pending(p)
+                } else {
+                    Inner::Start(p)
+                };
+                Poll::Pending
+            }
+            Inner::Pending(p) => {
+                if let Some(v) = p.poll_result(cx.waker()) {
+                    return Poll::Ready(v);
+                }
+                this.inner = Inner::Pending(p);
+                Poll::Pending
+            }
+            Inner::Finished => panic!("KernelFuture polled after completion"),
+        }
+    }
+}
+
+impl<T> Drop for KernelFuture<T> {
+    fn drop(&mut self) {
+        if let Inner::Pending(p) = &self.inner {
+            p.cancel();
+        }
+    }
+}
+
+struct SemTake(Arc<Semaphore>);
+
+impl KernelWait for SemTake {
+    type Output = ();
+
+    fn wait(&mut self, ticks: i32) -> Option<()> {
+        self.0.take(ticks).ok()
+    }
+
+    fn give_back(&mut self, _: ()) {
+        self.0.release();
+    }
+}
+
+struct QueueReceive<T: Send + 'static>(Arc<Queue<T>>);
+
+impl<T: Send + 'static> KernelWait for QueueReceive<T> {
+    type Output = T;
+
+    fn wait(&mut self, ticks: i32) -> Option<T> {
+        self.0.receive(ticks).ok()
+    }
+
+    /// The message is put back to the front of the queue
+    fn give_back(&mut self, val: T) {
+        self.0.give_back(val);
+    }
+}
+
+struct QueueSend<T: Send + 'static> {
+    queue: Arc<Queue<T>>,
+    item: Option<T>,
+}
+
+impl<T: Send + 'static> KernelWait for QueueSend<T> {
+    type Output = ();
+
+    fn wait(&mut self, ticks: i32) -> Option<()> {
+        let v = self.item.take()?;
+        match send_item(&self.queue, v, ticks) {
+            None => Some(()),
+            Some(v) => {
+                self.item = Some(v);
+                None
+            }
+        }
+    }
+
+    /* a sent message can not be taken back */
+    fn give_back(&mut self, _: ()) {}
+}
+
+/// Send `v` to the queue, gives it back on timeout
+fn send_item<T>(queue: &Queue<T>, v: T, ticks: i32) -> Option<T> {
+    let s = Box::into_raw(Box::new(v));
+    let r = rttbase_queue_send_wait(
+        queue.raw(),
+        &s as *const _ as *const CVoid,
+        Queue::<T>::mem_size(),
+        ticks,
+    );
+    if r == 0 {
+        None
+    } else {
+        Some(unsafe { *Box::from_raw(s) })
+    }
+}
+
+impl Semaphore {
+    /// Take the semaphore without blocking the executor thread
+    pub fn take_async(self: &Arc<Self>) -> KernelFuture<()> {
+        KernelFuture::new(SemTake(self.clone()))
+    }
+}
+
+impl<T: Send + 'static> Queue<T> {
+    /// Receive a message without blocking the executor thread
+    ///
+    /// # Note
+    /// If the future is dropped just as a message is received, the message
+    /// is put back to the front of the queue, so no message is lost or reordered.
+    /// Should the queue have been filled up meanwhile,
+    /// the next `receive` or `receive_async` returns it.
+    pub fn receive_async(self: &Arc<Self>) -> KernelFuture<T> {
+        KernelFuture::new(QueueReceive(self.clone()))
+    }
+
+    /// Send a message without blocking the executor thread while the queue is full
+    pub fn send_async(self: &Arc<Self>, item: T) -> KernelFuture<()> {
+        KernelFuture::new(QueueSend {
+            queue: self.clone(),
+            item: Some(item),
+        })
+    }
+}
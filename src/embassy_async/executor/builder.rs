@@ -0,0 +1,111 @@
+use super::{Executor, SendSpawner, Spawner};
+use crate::base::RTTError;
+use crate::semaphore::Semaphore;
+use crate::thread::{Thread, ThreadBuilder};
+use crate::{Arc, Box};
+use core::cell::UnsafeCell;
+
+/// Run an `Executor` on a new thread
+///
+/// Executors on threads of different priorities preempt each other like their threads,
+/// so latency critical tasks can be run on a high priority executor.
+///
+/// # Example
+/// ```
+/// use rtt_rs::embassy_async::executor::Executor;
+///
+/// let fast = Executor::builder()
+///     .name("fast")
+///     .priority(5)
+///     .start(|spawner| spawner.must_spawn(control()))
+///     .unwrap();
+///
+/// let bulk = Executor::builder()
+///     .name("bulk")
+///     .priority(20)
+///     .start(|spawner| spawner.must_spawn(logger()))
+///     .unwrap();
+///
+/// /* spawn Send tasks later from any thread */
+/// fast.must_spawn(alarm());
+/// ```
+pub struct ExecutorBuilder {
+    thread: ThreadBuilder,
+    idle_timeout: i32,
+}
+
+struct Packet {
+    spawner: UnsafeCell<Option<SendSpawner>>,
+    ready: Semaphore,
+}
+
+unsafe impl Send for Packet {}
+unsafe impl Sync for Packet {}
+
+impl ExecutorBuilder {
+    pub(crate) fn new() -> Self {
+        let mut thread = Thread::new();
+        thread.name("executor");
+        ExecutorBuilder {
+            thread,
+            idle_timeout: -1,
+        }
+    }
+
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.thread.name(name);
+        self
+    }
+
+    pub fn stack_size(&mut self, stack_size: u32) -> &mut Self {
+        self.thread.stack_size(stack_size);
+        self
+    }
+
+    pub fn priority(&mut self, priority: u8) -> &mut Self {
+        self.thread.priority(priority);
+        self
+    }
+
+    pub fn ticks(&mut self, ticks: u32) -> &mut Self {
+        self.thread.ticks(ticks);
+        self
+    }
+
+    /// See `Executor::idle_timeout`
+    pub fn idle_timeout(&mut self, ticks: i32) -> &mut Self {
+        self.idle_timeout = ticks;
+        self
+    }
+
+    /// Start the thread and run the executor on it
+    ///
+    /// `init` is called on the new thread to spawn the first tasks,
+    /// which do not need to be `Send`. Returns after `init` with a
+    /// spawner for `Send` tasks.
+    pub fn start<F>(&self, init: F) -> Result<SendSpawner, RTTError>
+    where
+        F: FnOnce(Spawner) + Send + 'static,
+    {
+        let packet = Arc::new(Packet {
+            spawner: UnsafeCell::new(None),
+            ready: Semaphore::new()?,
+        });
+
+        let their_packet = packet.clone();
+        let idle_timeout = self.idle_timeout;
+        self.thread.start(move || {
+            /* the thread never ends, so the executor lives forever */
+            let executor = Box::leak(Box::new(Executor::new()));
+            executor.idle_timeout(idle_timeout);
+            executor.run(move |spawner| {
+                init(spawner);
+                unsafe { *their_packet.spawner.get() = Some(spawner.make_send()) };
+                their_packet.ready.release();
+            })
+        })?;
+
+        let _ = packet.ready.take_wait_forever();
+        Ok(unsafe { (*packet.spawner.get()).take().unwrap() })
+    }
+}
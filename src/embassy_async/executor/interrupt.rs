@@ -0,0 +1,98 @@
+use super::{raw, SendSpawner};
+use atomic_polyfill::{AtomicBool, Ordering};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+
+/// Interrupt mode executor.
+///
+/// The executor is polled by an interrupt service function, usually a free
+/// (software triggered) interrupt of the chip. Its tasks preempt every thread
+/// and every thread mode executor.
+///
+/// `pend` must trigger the interrupt, and the interrupt must call `on_interrupt`.
+///
+/// # Note
+/// The tasks run in interrupt context: they must not block or allocate.
+/// The `SendSpawner` returned by `start` can not spawn heap allocated tasks.
+///
+/// `on_interrupt` itself only calls kernel functions which are safe in an
+/// interrupt: `rt_tick_get`, and with feature `time` `rt_timer_stop`,
+/// `rt_timer_control` and `rt_timer_start` to set the alarm. The alarm timer is
+/// created by `start` in thread context. Freeing finished tasks is not interrupt
+/// safe, without boxed tasks there are none to free.
+///
+/// # Example
+/// ```
+/// use rtt_rs::embassy_async::executor::InterruptExecutor;
+///
+/// static EXECUTOR: InterruptExecutor = InterruptExecutor::new();
+///
+/// fn pend() { /* set the pending bit of the software interrupt */ }
+///
+/// #[no_mangle]
+/// extern "C" fn SWI_IRQHandler() {
+///     unsafe { EXECUTOR.on_interrupt() }
+/// }
+///
+/// let spawner = EXECUTOR.start(pend);
+/// spawner.must_spawn(control());
+/// ```
+pub struct InterruptExecutor {
+    /* set by the first `start` */
+    started: AtomicBool,
+    /* set once the executor is written */
+    ready: AtomicBool,
+    executor: UnsafeCell<MaybeUninit<raw::Executor>>,
+}
+
+unsafe impl Send for InterruptExecutor {}
+unsafe impl Sync for InterruptExecutor {}
+
+fn signal_pend(ctx: *mut ()) {
+    let pend: fn() = unsafe { core::mem::transmute(ctx) };
+    pend();
+}
+
+impl InterruptExecutor {
+    pub const fn new() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            ready: AtomicBool::new(false),
+            executor: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Start the executor, `pend` is called when it needs to be polled
+    ///
+    /// Returns a spawner for `Send` tasks.
+    ///
+    /// # Panics
+    /// Panics if the executor is already started
+    pub fn start(&'static self, pend: fn()) -> SendSpawner {
+        if self.started.swap(true, Ordering::AcqRel) {
+            panic!("InterruptExecutor::start called twice");
+        }
+
+        let executor = unsafe {
+            (*self.executor.get())
+                .as_mut_ptr()
+                .write(raw::Executor::new(signal_pend, pend as *mut ()));
+            &*(*self.executor.get()).as_ptr()
+        };
+        self.ready.store(true, Ordering::Release);
+        executor.send_spawner()
+    }
+
+    /// Poll the executor, call it from the interrupt service function
+    ///
+    /// # Safety
+    /// Must not be called reentrantly, i.e. only from one interrupt
+    /// which does not preempt itself. Does nothing before `start`.
+    pub unsafe fn on_interrupt(&'static self) {
+        if !self.ready.load(Ordering::Acquire) {
+            return;
+        }
+        let executor = &*(*self.executor.get()).as_ptr();
+        executor.poll();
+    }
+}
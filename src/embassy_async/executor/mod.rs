@@ -1,26 +1,36 @@
 //! Async task executor.
-mod raw;
+mod builder;
+mod interrupt;
+pub(crate) mod raw;
 mod spawner;
 
 use core::marker::PhantomData;
 
 use crate::base::CVoid;
 use crate::embassy_async::executor::raw::{task_from_waker, wake_task};
-use crate::raw_api::{rt_thread_resume, rt_thread_self, rt_thread_suspend};
+use crate::semaphore::{rttbase_semaphore_release, Semaphore};
 use core::task::Waker;
-pub use spawner::*;
+pub use builder::ExecutorBuilder;
+pub use interrupt::InterruptExecutor;
 pub use raw::*;
+pub use spawner::*;
 
+const RT_WAITING_FOREVER: i32 = -1;
+
+/// Thread-mode executor.
+///
+/// The executor thread sleeps on a kernel semaphore between polls.
+/// Every signal releases the semaphore, so a wake that arrives
+/// while the executor is polling is never lost.
 pub struct Executor {
     inner: raw::Executor,
-    thread: *mut CVoid,
+    signal: Semaphore,
+    idle_timeout: i32,
     not_send: PhantomData<*mut ()>,
 }
 
-fn wake_thread(th: *mut CVoid) {
-    unsafe {
-        rt_thread_resume(th as _);
-    }
+fn signal_semaphore(sem: *mut ()) {
+    rttbase_semaphore_release(sem as *const CVoid);
 }
 
 pub fn device_wake(c: Waker) {
@@ -32,15 +42,33 @@ pub fn device_wake(c: Waker) {
 
 impl Executor {
     /// Create a new Executor.
+    ///
+    /// # Panics
+    /// Panics if the kernel semaphore can not be created
     pub fn new() -> Self {
-        let th = unsafe { rt_thread_self() };
+        let signal = Semaphore::new().unwrap();
         Self {
-            inner: raw::Executor::new(|th| wake_thread(th as _), th as _),
-            thread: th as _,
+            inner: raw::Executor::new(signal_semaphore, signal.raw() as _),
+            signal,
+            idle_timeout: RT_WAITING_FOREVER,
             not_send: PhantomData,
         }
     }
 
+    /// Run a new executor on a new thread, see [`ExecutorBuilder`].
+    pub fn builder() -> ExecutorBuilder {
+        ExecutorBuilder::new()
+    }
+
+    /// Poll all tasks at least every `ticks` ticks, even without any wake.
+    ///
+    /// Useful for tasks polling hardware that can not wake them.
+    /// The default is to sleep until a task is woken.
+    pub fn idle_timeout(&mut self, ticks: i32) -> &mut Self {
+        self.idle_timeout = ticks;
+        self
+    }
+
     /// Run the executor.
     ///
     /// The `init` closure is called with a [`Spawner`] that spawns tasks on
@@ -49,6 +77,7 @@ impl Executor {
     ///
     /// To spawn more tasks later, you may keep copies of the [`Spawner`] (it is `Copy`),
     /// for example by passing it as an argument to the initial tasks.
+    /// To spawn tasks from other threads, convert it with [`Spawner::make_send`].
     ///
     /// This function requires `&'static mut self`. This means you have to store the
     /// Executor instance in a place where it'll live forever and grants you mutable
@@ -63,10 +92,14 @@ impl Executor {
         init(self.inner.spawner());
 
         loop {
-            unsafe {
-                self.inner.poll();
-                rt_thread_suspend(self.thread as _);
-            };
+            // A single poll serves all the signals received so far,
+            // signals arriving from now on are counted again.
+            while let Ok(_) = self.signal.try_take() {}
+
+            unsafe { self.inner.poll() };
+
+            // A timeout is not an error, it is the idle poll.
+            let _ = self.signal.take(self.idle_timeout);
         }
     }
 }
@@ -0,0 +1,278 @@
+//! Heap allocated tasks.
+//!
+//! A boxed task is reference counted: the executor holds one reference until the
+//! task has finished and left all queues, the [`JoinHandle`] holds one, and every
+//! cloned `Waker` holds one. When the last reference is gone the task is pushed to
+//! the executor's free queue and deallocated by the next `poll`, so memory is never
+//! freed from an interrupt service function.
+
+use atomic_polyfill::{AtomicU32, Ordering};
+use core::cell::{Cell, UnsafeCell};
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::ptr;
+use core::ptr::NonNull;
+use core::task::{Context, Poll, Waker};
+
+use super::util::UninitCell;
+use super::{waker, Executor, TaskHeader, STATE_BOXED, STATE_RUN_QUEUED, STATE_SPAWNED};
+#[cfg(feature = "time")]
+use super::STATE_TIMER_QUEUED;
+use crate::raw_api::no_irq;
+use crate::Box;
+
+/// Task was aborted by [`JoinHandle::abort`]
+pub(crate) const STATE_ABORTED: u32 = 1 << 4;
+
+#[cfg(feature = "time")]
+const STATE_QUEUED: u32 = STATE_RUN_QUEUED | STATE_TIMER_QUEUED;
+#[cfg(not(feature = "time"))]
+const STATE_QUEUED: u32 = STATE_RUN_QUEUED;
+
+// repr(C) is needed to guarantee that the TaskHeader is located at offset 0,
+// and that `JoinSlot` has the same offset in `BoxedPrefix<T>` and `BoxedTask<F>`.
+#[repr(C)]
+struct BoxedHeader {
+    raw: TaskHeader,
+    refs: AtomicU32,
+    free_fn: unsafe fn(NonNull<TaskHeader>),
+    done_next: Cell<*mut TaskHeader>,
+}
+
+#[repr(C)]
+struct JoinSlot<T> {
+    result: UnsafeCell<Option<Result<T, JoinError>>>,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+#[repr(C)]
+struct BoxedPrefix<T> {
+    head: BoxedHeader,
+    join: JoinSlot<T>,
+}
+
+#[repr(C)]
+struct BoxedTask<F: Future> {
+    head: BoxedHeader,
+    join: JoinSlot<F::Output>,
+    future: UninitCell<F>, // Valid if STATE_SPAWNED
+}
+
+impl<F: Future + 'static> BoxedTask<F> {
+    /// Allocate a task in spawned state with two references:
+    /// one for the executor and one for the `JoinHandle`.
+    fn allocate(future: F) -> NonNull<TaskHeader> {
+        let task = Box::new(BoxedTask {
+            head: BoxedHeader {
+                raw: TaskHeader::new(),
+                refs: AtomicU32::new(2),
+                free_fn: Self::free,
+                done_next: Cell::new(ptr::null_mut()),
+            },
+            join: JoinSlot {
+                result: UnsafeCell::new(None),
+                waker: UnsafeCell::new(None),
+            },
+            future: UninitCell::uninit(),
+        });
+
+        unsafe {
+            task.head.raw.poll_fn.write(Self::poll);
+            task.future.write(future);
+        }
+        task.head.raw.state.store(
+            STATE_SPAWNED | STATE_RUN_QUEUED | STATE_BOXED,
+            Ordering::Release,
+        );
+
+        NonNull::from(Box::leak(task)).cast()
+    }
+
+    unsafe fn poll(p: NonNull<TaskHeader>) {
+        let this = &*(p.as_ptr() as *const Self);
+
+        let state = this.head.raw.state.load(Ordering::Acquire);
+        let result = if state & STATE_ABORTED != 0 {
+            Err(JoinError::Aborted)
+        } else {
+            let future = Pin::new_unchecked(this.future.as_mut());
+            let waker = waker::from_boxed_task(p);
+            let mut cx = Context::from_waker(&waker);
+            let poll = future.poll(&mut cx);
+
+            // The waker was created without taking a reference.
+            core::mem::forget(waker);
+
+            match poll {
+                Poll::Ready(out) => Ok(out),
+                Poll::Pending => return,
+            }
+        };
+
+        this.future.drop_in_place();
+        #[cfg(feature = "time")]
+        this.head.raw.finish_timer();
+        this.head.raw.state.fetch_and(!STATE_SPAWNED, Ordering::AcqRel);
+
+        complete(&this.join, result);
+
+        let executor = &*this.head.raw.executor.get();
+        this.head.done_next.set(executor.boxed_done.get());
+        executor.boxed_done.set(p.as_ptr());
+    }
+
+    unsafe fn free(p: NonNull<TaskHeader>) {
+        drop(Box::from_raw(p.as_ptr() as *mut Self));
+    }
+}
+
+fn complete<T>(join: &JoinSlot<T>, result: Result<T, JoinError>) {
+    let waker = no_irq(|| unsafe {
+        *join.result.get() = Some(result);
+        (*join.waker.get()).take()
+    });
+    if let Some(w) = waker {
+        w.wake();
+    }
+}
+
+/// Take a reference to a boxed task.
+pub(crate) unsafe fn acquire(p: NonNull<TaskHeader>) {
+    let head = &*(p.as_ptr() as *const BoxedHeader);
+    head.refs.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Drop a reference to a boxed task.
+///
+/// `in_poll` must be true only when called by the executor inside `poll`,
+/// otherwise the executor is signalled to free the task.
+pub(crate) unsafe fn release(p: NonNull<TaskHeader>, in_poll: bool) {
+    let head = &*(p.as_ptr() as *const BoxedHeader);
+    if head.refs.fetch_sub(1, Ordering::AcqRel) != 1 {
+        return;
+    }
+
+    // Last reference: the task is finished and in no queue, so the run
+    // queue link is free to be used by the free queue.
+    let executor = &*head.raw.executor.get();
+    if executor.free_queue.enqueue(p.as_ptr()) && !in_poll {
+        (executor.signal_fn)(executor.signal_ctx)
+    }
+}
+
+impl Executor {
+    /// Drop the executor references of finished boxed tasks which left all queues,
+    /// then deallocate the tasks without references.
+    ///
+    /// Called at the end of `poll`.
+    pub(crate) unsafe fn collect_boxed(&self) {
+        let mut prev = &self.boxed_done;
+        while !prev.get().is_null() {
+            let p = NonNull::new_unchecked(prev.get());
+            let head = &*(p.as_ptr() as *const BoxedHeader);
+            if head.raw.state.load(Ordering::Acquire) & STATE_QUEUED != 0 {
+                // Still referenced by a queue, retry on the next poll
+                prev = &head.done_next;
+            } else {
+                prev.set(head.done_next.get());
+                release(p, true);
+            }
+        }
+
+        self.free_queue.dequeue_all(|p| {
+            let head = &*(p.as_ptr() as *const BoxedHeader);
+            #[cfg(feature = "trace")]
+            self.trace_remove(p);
+            (head.free_fn)(p);
+        });
+    }
+
+    /// Spawn a heap allocated task in this executor.
+    ///
+    /// # Safety
+    ///
+    /// It is OK to call this from a thread that's not the executor thread
+    /// only if the future is Send.
+    pub(crate) unsafe fn spawn_boxed<F: Future + 'static>(
+        &'static self,
+        future: F,
+    ) -> JoinHandle<F::Output> {
+        let task = BoxedTask::allocate(future);
+        self.spawn(task);
+        JoinHandle {
+            task,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Error returned by awaiting a [`JoinHandle`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JoinError {
+    /// The task was aborted before it finished.
+    Aborted,
+}
+
+/// Handle to a task spawned with `Spawner::spawn_boxed`.
+///
+/// Awaiting it returns the output of the task. Dropping it detaches the task,
+/// which keeps running and is deallocated when it finishes.
+pub struct JoinHandle<T> {
+    task: NonNull<TaskHeader>,
+    phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for JoinHandle<T> {}
+unsafe impl<T: Send> Sync for JoinHandle<T> {}
+
+impl<T> JoinHandle<T> {
+    fn prefix(&self) -> &BoxedPrefix<T> {
+        unsafe { &*(self.task.as_ptr() as *const BoxedPrefix<T>) }
+    }
+
+    /// Abort the task.
+    ///
+    /// The future is dropped the next time the executor polls the task,
+    /// awaiting the handle then returns `JoinError::Aborted`.
+    /// Aborting a finished task does nothing.
+    pub fn abort(&self) {
+        let raw = &self.prefix().head.raw;
+        raw.state.fetch_or(STATE_ABORTED, Ordering::AcqRel);
+        unsafe { raw.enqueue() };
+    }
+
+    /// Check if the task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.prefix().head.raw.state.load(Ordering::Acquire) & STATE_SPAWNED == 0
+    }
+}
+
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let join = &self.prefix().join;
+        let (ret, old_waker) = no_irq(|| unsafe {
+            match (*join.result.get()).take() {
+                Some(r) => (Poll::Ready(r), None),
+                None => (
+                    Poll::Pending,
+                    (*join.waker.get()).replace(cx.waker().clone()),
+                ),
+            }
+        });
+
+        // Dropping a waker may release a task, do it with interrupts enabled.
+        drop(old_waker);
+        ret
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        unsafe { release(self.task, false) }
+    }
+}
@@ -7,7 +7,12 @@
 //! Using this module requires respecting subtle safety contracts. If you can, prefer using the safe
 //! executor wrappers in [`crate::executor`] and the [`crate::task`] macro, which are fully safe.
 
+mod boxed;
 mod run_queue;
+#[cfg(feature = "time")]
+mod timer_queue;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub(crate) mod util;
 mod waker;
 
@@ -22,13 +27,23 @@ use core::{mem, ptr};
 use self::run_queue::{RunQueue, RunQueueItem};
 use self::util::UninitCell;
 use super::SpawnToken;
+#[cfg(feature = "time")]
+use crate::embassy_async::time::driver::Alarm;
+#[cfg(feature = "time")]
+use crate::embassy_async::time::Instant;
 
+pub use self::boxed::{JoinError, JoinHandle};
 pub use self::waker::task_from_waker;
 
 /// Task is spawned (has a future)
 pub(crate) const STATE_SPAWNED: u32 = 1 << 0;
 /// Task is in the executor run queue
 pub(crate) const STATE_RUN_QUEUED: u32 = 1 << 1;
+/// Task is in the executor timer queue
+#[cfg(feature = "time")]
+pub(crate) const STATE_TIMER_QUEUED: u32 = 1 << 2;
+/// Task is allocated on the heap by `spawn_boxed`
+pub(crate) const STATE_BOXED: u32 = 1 << 3;
 
 /// Raw task header for use in task pointers.
 ///
@@ -40,6 +55,14 @@ pub struct TaskHeader {
     pub(crate) executor: Cell<*const Executor>,
     // Valid if state != 0
     pub(crate) poll_fn: UninitCell<unsafe fn(NonNull<TaskHeader>)>, // Valid if STATE_SPAWNED
+
+    #[cfg(feature = "time")]
+    pub(crate) expires_at: Cell<Instant>,
+    #[cfg(feature = "time")]
+    pub(crate) timer_queue_item: timer_queue::TimerQueueItem,
+
+    #[cfg(feature = "trace")]
+    pub(crate) trace: trace::TaskTrace,
 }
 
 impl TaskHeader {
@@ -49,10 +72,21 @@ impl TaskHeader {
             run_queue_item: RunQueueItem::new(),
             executor: Cell::new(ptr::null()),
             poll_fn: UninitCell::uninit(),
+
+            #[cfg(feature = "time")]
+            expires_at: Cell::new(Instant::from_ticks(0)),
+            #[cfg(feature = "time")]
+            timer_queue_item: timer_queue::TimerQueueItem::new(),
+
+            #[cfg(feature = "trace")]
+            trace: trace::TaskTrace::new(),
         }
     }
 
     pub(crate) unsafe fn enqueue(&self) {
+        #[cfg(feature = "trace")]
+        self.trace.wake();
+
         let mut current = self.state.load(Ordering::Acquire);
         loop {
             // If already scheduled, or if not started,
@@ -78,6 +112,19 @@ impl TaskHeader {
         let executor = &*self.executor.get();
         executor.enqueue(self as *const TaskHeader as *mut TaskHeader);
     }
+
+    /// Remove a finished task from the timer queue.
+    ///
+    /// Must be called by the executor thread before `STATE_SPAWNED` is cleared,
+    /// so the task can be spawned again right away.
+    #[cfg(feature = "time")]
+    pub(crate) unsafe fn finish_timer(&self) {
+        self.expires_at.set(Instant::MAX);
+        if self.state.load(Ordering::Acquire) & STATE_TIMER_QUEUED != 0 {
+            let executor = &*self.executor.get();
+            executor.timer_queue.remove(NonNull::from(self));
+        }
+    }
 }
 
 /// Raw storage in which a task can be spawned.
@@ -89,7 +136,7 @@ impl TaskHeader {
 /// A `TaskStorage` must live forever, it may not be deallocated even after the task has finished
 /// running. Hence the relevant methods require `&'static self`. It may be reused, however.
 ///
-/// Internally, the [embassy::task](crate::task) macro allocates an array of `TaskStorage`s
+/// Internally, the [rtt_rs::task](crate::task) macro allocates an array of `TaskStorage`s
 /// in a `static`. The most common reason to use the raw `Task` is to have control of where
 /// the memory for the task is allocated: on the stack, or on the heap with e.g. `Box::leak`, etc.
 
@@ -126,6 +173,27 @@ impl<F: Future + 'static> TaskStorage<F> {
         SpawnToken::new_failed()
     }
 
+    /// Try to spawn a task in a pool, naming it for the task statistics.
+    ///
+    /// Same as [`Self::spawn_pool()`], the name is only kept with feature `trace`.
+    pub fn spawn_pool_named(
+        pool: &'static [Self],
+        name: &'static str,
+        future: impl FnOnce() -> F,
+    ) -> SpawnToken<F> {
+        for task in pool {
+            if task.spawn_allocate() {
+                #[cfg(feature = "trace")]
+                task.raw.trace.set_name(name);
+                #[cfg(not(feature = "trace"))]
+                let _ = name;
+                return unsafe { task.spawn_initialize(future) };
+            }
+        }
+
+        SpawnToken::new_failed()
+    }
+
     /// Try to spawn the task.
     ///
     /// The `future` closure constructs the future. It's only called if spawning is
@@ -172,6 +240,8 @@ impl<F: Future + 'static> TaskStorage<F> {
         match future.poll(&mut cx) {
             Poll::Ready(_) => {
                 this.future.drop_in_place();
+                #[cfg(feature = "time")]
+                this.raw.finish_timer();
                 this.raw.state.fetch_and(!STATE_SPAWNED, Ordering::AcqRel);
             }
             Poll::Pending => {}
@@ -208,6 +278,19 @@ pub struct Executor {
     run_queue: RunQueue,
     signal_fn: fn(*mut ()),
     signal_ctx: *mut (),
+
+    /// Finished boxed tasks still referenced by the executor
+    boxed_done: Cell<*mut TaskHeader>,
+    /// Boxed tasks without references, to be deallocated
+    free_queue: RunQueue,
+
+    #[cfg(feature = "time")]
+    pub(crate) timer_queue: timer_queue::TimerQueue,
+    #[cfg(feature = "time")]
+    alarm: Alarm,
+
+    #[cfg(feature = "trace")]
+    trace: trace::ExecutorTrace,
 }
 
 impl Executor {
@@ -219,19 +302,23 @@ impl Executor {
     /// See [`Executor`] docs for details on `signal_fn`.
     pub fn new(signal_fn: fn(*mut ()), signal_ctx: *mut ()) -> Self {
         #[cfg(feature = "time")]
-            let alarm = unsafe { unwrap!(driver::allocate_alarm()) };
-        #[cfg(feature = "time")]
-            driver::set_alarm_callback(alarm, signal_fn, signal_ctx);
+        let alarm = Alarm::new(signal_fn, signal_ctx);
 
         Self {
             run_queue: RunQueue::new(),
             signal_fn,
             signal_ctx,
 
+            boxed_done: Cell::new(ptr::null_mut()),
+            free_queue: RunQueue::new(),
+
             #[cfg(feature = "time")]
             timer_queue: timer_queue::TimerQueue::new(),
             #[cfg(feature = "time")]
             alarm,
+
+            #[cfg(feature = "trace")]
+            trace: trace::ExecutorTrace::new(),
         }
     }
 
@@ -259,6 +346,8 @@ impl Executor {
     pub(super) unsafe fn spawn(&'static self, task: NonNull<TaskHeader>) {
         let task = task.as_ref();
         task.executor.set(self);
+        #[cfg(feature = "trace")]
+        self.trace_spawn(task);
         self.enqueue(task as *const _ as _);
     }
 
@@ -281,7 +370,7 @@ impl Executor {
     /// no `poll()` already running.
     pub unsafe fn poll(&'static self) {
         #[cfg(feature = "time")]
-            self.timer_queue.dequeue_expired(Instant::now(), |p| {
+        self.timer_queue.dequeue_expired(Instant::now(), |p| {
             p.as_ref().enqueue();
         });
 
@@ -289,7 +378,7 @@ impl Executor {
             let task = p.as_ref();
 
             #[cfg(feature = "time")]
-                task.expires_at.set(Instant::MAX);
+            task.expires_at.set(Instant::MAX);
 
             let state = task.state.fetch_and(!STATE_RUN_QUEUED, Ordering::AcqRel);
             if state & STATE_SPAWNED == 0 {
@@ -302,8 +391,28 @@ impl Executor {
             }
 
             // Run the task
+            #[cfg(feature = "trace")]
+            let start = trace::now();
+
             task.poll_fn.read()(p as _);
+
+            #[cfg(feature = "trace")]
+            task.trace.polled(trace::now().wrapping_sub(start));
+
+            // Enqueue or update into timer_queue
+            #[cfg(feature = "time")]
+            self.timer_queue.update(p);
         });
+
+        #[cfg(feature = "time")]
+        {
+            // If this is already in the past, the alarm fires on the next tick.
+            // This will cause `signal_fn` to be called, which will cause `poll()` to be called again.
+            let next_expiration = self.timer_queue.next_expiration();
+            self.alarm.set(next_expiration.as_ticks());
+        }
+
+        self.collect_boxed();
     }
 
     /// Get a spawner that spawns tasks in this executor.
@@ -313,6 +422,11 @@ impl Executor {
     pub fn spawner(&'static self) -> super::Spawner {
         super::Spawner::new(self)
     }
+
+    /// Get a spawner that spawns Send tasks in this executor from any thread.
+    pub fn send_spawner(&'static self) -> super::SendSpawner {
+        super::SendSpawner::new(self)
+    }
 }
 
 /// Wake a task by raw pointer.
@@ -321,3 +435,15 @@ impl Executor {
 pub unsafe fn wake_task(task: NonNull<TaskHeader>) {
     task.as_ref().enqueue();
 }
+
+/// Ask the executor to poll the task of `waker` again at `at`.
+///
+/// Only valid while the task is being polled, the executor
+/// puts it into the timer queue once the poll returns.
+#[cfg(feature = "time")]
+pub(crate) unsafe fn register_timer(at: Instant, waker: &core::task::Waker) {
+    let task = task_from_waker(waker);
+    let task = task.as_ref();
+    let expires_at = task.expires_at.get();
+    task.expires_at.set(expires_at.min(at));
+}
@@ -0,0 +1,268 @@
+//! Per-task statistics, only available with feature `trace`.
+//!
+//! Every executor a task was spawned on keeps a list of its tasks. The executor
+//! counts the wakes and polls of every task and measures each poll with the
+//! trace clock, which is `rt_tick_get` unless replaced by [`set_clock`].
+
+use atomic_polyfill::{AtomicUsize, Ordering};
+use core::cell::{Cell, UnsafeCell};
+use core::ptr;
+use core::ptr::NonNull;
+
+use super::{Executor, TaskHeader, STATE_SPAWNED};
+use crate::raw_api::no_irq;
+use crate::thread::Thread;
+use crate::vec::Vec;
+
+/// Trace clock, 0 means `rt_tick_get`
+static CLOCK: AtomicUsize = AtomicUsize::new(0);
+
+struct ExecutorList(Cell<*const Executor>);
+
+unsafe impl Sync for ExecutorList {}
+
+/// Executors with traced tasks, only modified with interrupts disabled
+static EXECUTORS: ExecutorList = ExecutorList(Cell::new(ptr::null()));
+
+/// Replace the trace clock, e.g. by a cycle counter for a better resolution.
+///
+/// The clock may wrap around, a single poll must be shorter than a full period.
+pub fn set_clock(clock: fn() -> u32) {
+    CLOCK.store(clock as usize, Ordering::Relaxed);
+}
+
+pub(crate) fn now() -> u32 {
+    match CLOCK.load(Ordering::Relaxed) {
+        0 => Thread::tick(),
+        f => unsafe { core::mem::transmute::<usize, fn() -> u32>(f)() },
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Counters {
+    polls: u32,
+    wakes: u32,
+    total_time: u64,
+    max_time: u32,
+}
+
+const ZERO: Counters = Counters {
+    polls: 0,
+    wakes: 0,
+    total_time: 0,
+    max_time: 0,
+};
+
+/// Trace state in every `TaskHeader`
+pub(crate) struct TaskTrace {
+    name: Cell<&'static str>,
+    /* executor whose task list holds this task */
+    executor: Cell<*const Executor>,
+    next: Cell<*const TaskHeader>,
+    counters: UnsafeCell<Counters>,
+}
+
+impl TaskTrace {
+    pub(crate) const fn new() -> Self {
+        Self {
+            name: Cell::new("task"),
+            executor: Cell::new(ptr::null()),
+            next: Cell::new(ptr::null()),
+            counters: UnsafeCell::new(ZERO),
+        }
+    }
+
+    pub(crate) fn set_name(&self, name: &'static str) {
+        self.name.set(name);
+    }
+
+    /// Called on every wake, from any context
+    pub(crate) fn wake(&self) {
+        no_irq(|| unsafe {
+            let c = &mut *self.counters.get();
+            c.wakes = c.wakes.wrapping_add(1)
+        });
+    }
+
+    /// Called by the executor after every poll
+    pub(crate) fn polled(&self, time: u32) {
+        no_irq(|| unsafe {
+            let c = &mut *self.counters.get();
+            c.polls = c.polls.wrapping_add(1);
+            c.total_time += time as u64;
+            c.max_time = c.max_time.max(time);
+        });
+    }
+}
+
+/// Trace state in every `Executor`
+pub(crate) struct ExecutorTrace {
+    registered: Cell<bool>,
+    next: Cell<*const Executor>,
+    tasks: Cell<*const TaskHeader>,
+}
+
+impl ExecutorTrace {
+    pub(crate) const fn new() -> Self {
+        Self {
+            registered: Cell::new(false),
+            next: Cell::new(ptr::null()),
+            tasks: Cell::new(ptr::null()),
+        }
+    }
+}
+
+impl Executor {
+    /// Add a task to the task list of this executor, called on spawn
+    pub(crate) fn trace_spawn(&'static self, task: &TaskHeader) {
+        no_irq(|| unsafe {
+            if !self.trace.registered.get() {
+                self.trace.registered.set(true);
+                self.trace.next.set(EXECUTORS.0.get());
+                EXECUTORS.0.set(self);
+            }
+
+            let old = task.trace.executor.get();
+            if old == self as *const Executor {
+                return;
+            }
+            if !old.is_null() {
+                (*old).trace_unlink(task);
+            }
+            task.trace.executor.set(self);
+            task.trace.next.set(self.trace.tasks.get());
+            self.trace.tasks.set(task);
+        });
+    }
+
+    /// Remove a task from the task list before it is deallocated
+    pub(crate) fn trace_remove(&self, task: NonNull<TaskHeader>) {
+        no_irq(|| unsafe { self.trace_unlink(task.as_ref()) });
+    }
+
+    /* must be called with interrupts disabled */
+    unsafe fn trace_unlink(&self, task: &TaskHeader) {
+        let mut prev = &self.trace.tasks;
+        while !prev.get().is_null() {
+            let cur = &*prev.get();
+            if ptr::eq(cur, task) {
+                prev.set(cur.trace.next.get());
+                task.trace.executor.set(ptr::null());
+                return;
+            }
+            prev = &cur.trace.next;
+        }
+    }
+}
+
+/// Statistics of a task
+#[derive(Debug, Copy, Clone)]
+pub struct TaskStats {
+    /// Name given by `#[task]`, `task` for tasks spawned without a name
+    pub name: &'static str,
+    /// Index of the executor in the order of the first spawn on it
+    pub executor: usize,
+    /// The task is spawned and not finished
+    pub running: bool,
+    pub polls: u32,
+    pub wakes: u32,
+    /// Sum of all poll durations, in trace clock units
+    pub total_time: u64,
+    /// Longest poll, in trace clock units
+    pub max_time: u32,
+}
+
+/* must be called with interrupts disabled */
+unsafe fn for_each_task(mut f: impl FnMut(usize, &TaskHeader)) {
+    let mut count = 0;
+    let mut ex = EXECUTORS.0.get();
+    while !ex.is_null() {
+        count += 1;
+        ex = (*ex).trace.next.get();
+    }
+
+    /* executors are pushed to the front, number them from the back */
+    let mut ex = EXECUTORS.0.get();
+    while !ex.is_null() {
+        count -= 1;
+        let mut task = (*ex).trace.tasks.get();
+        while !task.is_null() {
+            f(count, &*task);
+            task = (*task).trace.next.get();
+        }
+        ex = (*ex).trace.next.get();
+    }
+}
+
+/// Get the statistics of all the tasks of all the executors
+pub fn snapshot() -> Vec<TaskStats> {
+    loop {
+        let mut n = 0;
+        no_irq(|| unsafe { for_each_task(|_, _| n += 1) });
+
+        /* allocate with interrupts enabled, retry if tasks were added meanwhile */
+        let mut ret = Vec::with_capacity(n);
+        let complete = no_irq(|| unsafe {
+            let mut complete = true;
+            for_each_task(|executor, t| {
+                if ret.len() == ret.capacity() {
+                    complete = false;
+                    return;
+                }
+                let c = *t.trace.counters.get();
+                ret.push(TaskStats {
+                    name: t.trace.name.get(),
+                    executor,
+                    running: t.state.load(Ordering::Relaxed) & STATE_SPAWNED != 0,
+                    polls: c.polls,
+                    wakes: c.wakes,
+                    total_time: c.total_time,
+                    max_time: c.max_time,
+                });
+            });
+            complete
+        });
+        if complete {
+            return ret;
+        }
+    }
+}
+
+/// Clear the counters of all the tasks
+pub fn reset() {
+    no_irq(|| unsafe { for_each_task(|_, t| *t.trace.counters.get() = ZERO) });
+}
+
+/// Print the statistics of all the tasks
+pub fn print() {
+    crate::print!(
+        "{:<4} {:<16} {:<4} {:>10} {:>10} {:>12} {:>10}\n",
+        "exec",
+        "task",
+        "run",
+        "polls",
+        "wakes",
+        "total",
+        "max"
+    );
+    for s in snapshot() {
+        crate::print!(
+            "{:<4} {:<16} {:<4} {:>10} {:>10} {:>12} {:>10}\n",
+            s.executor,
+            s.name,
+            if s.running { "yes" } else { "no" },
+            s.polls,
+            s.wakes,
+            s.total_time,
+            s.max_time
+        );
+    }
+}
+
+/// Print the task statistics from the shell
+///
+/// Export it in C with `MSH_CMD_EXPORT(rust_task_stats, print rust async task statistics);`
+#[no_mangle]
+pub extern "C" fn rust_task_stats() {
+    print();
+}
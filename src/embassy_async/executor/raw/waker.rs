@@ -2,10 +2,14 @@ use core::mem;
 use core::ptr::NonNull;
 use core::task::{RawWaker, RawWakerVTable, Waker};
 
-use super::TaskHeader;
+use super::{boxed, TaskHeader};
 
 const VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
 
+/// Wakers of heap allocated tasks hold a reference to the task.
+const BOXED_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(boxed_clone, boxed_wake, boxed_wake_by_ref, boxed_drop);
+
 unsafe fn clone(p: *const ()) -> RawWaker {
     RawWaker::new(p, &VTABLE)
 }
@@ -22,6 +26,29 @@ pub(crate) unsafe fn from_task(p: NonNull<TaskHeader>) -> Waker {
     Waker::from_raw(RawWaker::new(p.as_ptr() as _, &VTABLE))
 }
 
+unsafe fn boxed_clone(p: *const ()) -> RawWaker {
+    boxed::acquire(NonNull::new_unchecked(p as *mut TaskHeader));
+    RawWaker::new(p, &BOXED_VTABLE)
+}
+
+unsafe fn boxed_wake(p: *const ()) {
+    boxed_wake_by_ref(p);
+    boxed_drop(p);
+}
+
+unsafe fn boxed_wake_by_ref(p: *const ()) {
+    (*(p as *mut TaskHeader)).enqueue()
+}
+
+unsafe fn boxed_drop(p: *const ()) {
+    boxed::release(NonNull::new_unchecked(p as *mut TaskHeader), false)
+}
+
+/// The returned waker does not hold a reference, it must be forgotten, not dropped.
+pub(crate) unsafe fn from_boxed_task(p: NonNull<TaskHeader>) -> Waker {
+    Waker::from_raw(RawWaker::new(p.as_ptr() as _, &BOXED_VTABLE))
+}
+
 /// Get a task pointer from a waker.
 ///
 /// This can used as an optimization in wait queues to store task pointers
@@ -35,7 +62,7 @@ pub(crate) unsafe fn from_task(p: NonNull<TaskHeader>) -> Waker {
 /// Panics if the waker is not created by the Embassy executor.
 pub unsafe fn task_from_waker(waker: &Waker) -> NonNull<TaskHeader> {
     let hack: &WakerHack = mem::transmute(waker);
-    if hack.vtable != &VTABLE {
+    if hack.vtable != &VTABLE && hack.vtable != &BOXED_VTABLE {
         panic!("Found waker not created by the embassy executor.")
     }
     NonNull::new_unchecked(hack.data as *mut TaskHeader)
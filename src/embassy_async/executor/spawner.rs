@@ -1,3 +1,4 @@
+use core::future::Future;
 use core::marker::PhantomData;
 use core::mem;
 use core::ptr::NonNull;
@@ -6,7 +7,7 @@ use super::raw;
 
 /// Token to spawn a newly-created task in an executor.
 ///
-/// When calling a task function (like `#[embassy::task] async fn my_task() { ... }`), the returned
+/// When calling a task function (like `#[rtt_rs::task] async fn my_task() { ... }`), the returned
 /// value is a `SpawnToken` that represents an instance of the task, ready to spawn. You must
 /// then spawn it into an executor, typically with [`Spawner::spawn()`].
 ///
@@ -49,9 +50,9 @@ impl<F> Drop for SpawnToken<F> {
 pub enum SpawnError {
     /// Too many instances of this task are already running.
     ///
-    /// By default, a task marked with `#[embassy::task]` can only have one instance
+    /// By default, a task marked with `#[rtt_rs::task]` can only have one instance
     /// running at a time. You may allow multiple instances to run in parallel with
-    /// `#[embassy::task(pool_size = 4)]`, at the cost of higher RAM usage.
+    /// `#[rtt_rs::task(pool_size = 4)]`, at the cost of higher RAM usage.
     Busy,
 }
 
@@ -60,7 +61,8 @@ pub enum SpawnError {
 /// This Spawner can spawn any task (Send and non-Send ones), but it can
 /// only be used in the executor thread (it is not Send itself).
 ///
-/// If you want to spawn tasks from another thread, use [SendSpawner].
+/// If you want to spawn tasks from another thread, use [SendSpawner],
+/// obtained by [Spawner::make_send].
 #[derive(Copy, Clone)]
 pub struct Spawner {
     executor: &'static raw::Executor,
@@ -77,7 +79,7 @@ impl Spawner {
 
     /// Spawn a task into an executor.
     ///
-    /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy::task]).
+    /// You obtain the `token` by calling a task function (i.e. one marked with `#[rtt_rs::task]).
     pub fn spawn<F>(&self, token: SpawnToken<F>) -> Result<(), SpawnError> {
         let task = token.raw_task;
         mem::forget(token);
@@ -91,11 +93,80 @@ impl Spawner {
         }
     }
 
-    /// Used by the `embassy_macros::main!` macro to throw an error when spawn
-    /// fails. This is here to allow conditional use of `defmt::unwrap!`
-    /// without introducing a `defmt` feature in the `embassy_macros` package,
-    /// which would require use of `-Z namespaced-features`.
+    /// Used by the `rtt_rs::main` macro to panic when spawn fails.
     pub fn must_spawn<F>(&self, token: SpawnToken<F>) -> () {
         self.spawn(token).unwrap();
     }
+
+    /// Spawn a future as a heap allocated task.
+    ///
+    /// No `TaskStorage` is needed, the task is deallocated when it finishes.
+    /// Await the returned [`raw::JoinHandle`] to get the output of the future.
+    ///
+    /// # Example
+    /// ```
+    /// let h = spawner.spawn_boxed(async move { 1 + 1 });
+    /// assert_eq!(h.await, Ok(2));
+    /// ```
+    pub fn spawn_boxed<F: Future + 'static>(&self, future: F) -> raw::JoinHandle<F::Output> {
+        unsafe { self.executor.spawn_boxed(future) }
+    }
+
+    /// Convert this Spawner to a SendSpawner. This allows you to send the
+    /// spawner to other threads, but the spawner loses the ability to spawn
+    /// non-Send tasks.
+    pub fn make_send(&self) -> SendSpawner {
+        SendSpawner::new(self.executor)
+    }
+}
+
+/// Handle to spawn tasks into an executor from any thread.
+///
+/// This Spawner can be used from any thread (it is Send), and even from
+/// interrupt service functions, but it can only spawn Send tasks.
+/// The reason for this is spawning is effectively "sending" the tasks
+/// to the executor thread.
+///
+/// It can not spawn heap allocated tasks, the heap must not be used
+/// in interrupt service functions. Use [`Spawner::spawn_boxed`] on the executor thread.
+///
+/// If you want to spawn non-Send tasks, use [Spawner].
+#[derive(Copy, Clone)]
+pub struct SendSpawner {
+    executor: &'static raw::Executor,
+    not_send: PhantomData<*mut ()>,
+}
+
+unsafe impl Send for SendSpawner {}
+unsafe impl Sync for SendSpawner {}
+
+impl SendSpawner {
+    pub(crate) fn new(executor: &'static raw::Executor) -> Self {
+        Self {
+            executor,
+            not_send: PhantomData,
+        }
+    }
+
+    /// Spawn a task into an executor.
+    ///
+    /// You obtain the `token` by calling a task function (i.e. one marked with `#[rtt_rs::task]).
+    pub fn spawn<F: Send>(&self, token: SpawnToken<F>) -> Result<(), SpawnError> {
+        let task = token.raw_task;
+        mem::forget(token);
+
+        match task {
+            Some(task) => {
+                // The future is Send, so it is OK to move it to the executor thread.
+                unsafe { self.executor.spawn(task) };
+                Ok(())
+            }
+            None => Err(SpawnError::Busy),
+        }
+    }
+
+    /// Spawn a task into an executor, panicking on failure.
+    pub fn must_spawn<F: Send>(&self, token: SpawnToken<F>) {
+        self.spawn(token).unwrap();
+    }
 }
@@ -0,0 +1,109 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::MaybeDone;
+
+/// Future returned by `join`
+pub struct Join<A: Future, B: Future> {
+    a: MaybeDone<A>,
+    b: MaybeDone<B>,
+}
+
+/// Wait for both futures
+pub fn join<A: Future, B: Future>(a: A, b: B) -> Join<A, B> {
+    Join {
+        a: MaybeDone::Future(a),
+        b: MaybeDone::Future(b),
+    }
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        /* poll every future, even when an earlier one is pending */
+        let mut done = true;
+        done &= unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx);
+        done &= unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx);
+
+        if done {
+            Poll::Ready((this.a.take_output(), this.b.take_output()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Future returned by `join3`
+pub struct Join3<A: Future, B: Future, C: Future> {
+    a: MaybeDone<A>,
+    b: MaybeDone<B>,
+    c: MaybeDone<C>,
+}
+
+/// Wait for all three futures
+pub fn join3<A: Future, B: Future, C: Future>(a: A, b: B, c: C) -> Join3<A, B, C> {
+    Join3 {
+        a: MaybeDone::Future(a),
+        b: MaybeDone::Future(b),
+        c: MaybeDone::Future(c),
+    }
+}
+
+impl<A: Future, B: Future, C: Future> Future for Join3<A, B, C> {
+    type Output = (A::Output, B::Output, C::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut done = true;
+        done &= unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx);
+        done &= unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx);
+        done &= unsafe { Pin::new_unchecked(&mut this.c) }.poll(cx);
+
+        if done {
+            Poll::Ready((
+                this.a.take_output(),
+                this.b.take_output(),
+                this.c.take_output(),
+            ))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Future returned by `join_array`
+pub struct JoinArray<F: Future, const N: usize> {
+    futures: [MaybeDone<F>; N],
+}
+
+/// Wait for all the futures of an array, returns their outputs in the same order
+pub fn join_array<F: Future, const N: usize>(futures: [F; N]) -> JoinArray<F, N> {
+    JoinArray {
+        futures: futures.map(MaybeDone::Future),
+    }
+}
+
+impl<F: Future, const N: usize> Future for JoinArray<F, N> {
+    type Output = [F::Output; N];
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut done = true;
+        for f in this.futures.iter_mut() {
+            done &= unsafe { Pin::new_unchecked(f) }.poll(cx);
+        }
+
+        if done {
+            let mut i = 0;
+            Poll::Ready([(); N].map(|_| {
+                i += 1;
+                this.futures[i - 1].take_output()
+            }))
+        } else {
+            Poll::Pending
+        }
+    }
+}
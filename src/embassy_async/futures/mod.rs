@@ -0,0 +1,116 @@
+//! Combinators to wait on several futures at once
+//!
+//! None of them allocate, the futures are polled in place
+//! with the waker of the task awaiting the combinator.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::embassy_async::futures::{join, select, Either};
+//! use rtt_rs::embassy_async::time::{Duration, Timer};
+//!
+//! async fn work() {
+//!     let (a, b) = join(read_a(), read_b()).await;
+//!
+//!     match select(read_a(), Timer::after(Duration::from_millis(10))).await {
+//!         Either::First(v) => { /* ..... */ }
+//!         Either::Second(_) => { /* timeout */ }
+//!     }
+//!
+//!     let v = rtt_rs::select! {
+//!         v = read_a() => v,
+//!         v = read_b() => v + 1,
+//!     };
+//! }
+//! ```
+
+mod join;
+mod select;
+
+pub use join::{join, join3, join_array, Join, Join3, JoinArray};
+pub use select::{select, select3, select_array, Either, Either3, Select, Select3, SelectArray};
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/* a future, then its output after it completed */
+enum MaybeDone<F: Future> {
+    Future(F),
+    Done(F::Output),
+    Gone,
+}
+
+impl<F: Future> MaybeDone<F> {
+    /// Poll the future if it is not done, returns true when done
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool {
+        let this = unsafe { self.get_unchecked_mut() };
+        match this {
+            MaybeDone::Future(fut) => {
+                /* the future is never moved out of the pinned `MaybeDone` */
+                match unsafe { Pin::new_unchecked(fut) }.poll(cx) {
+                    Poll::Ready(out) => {
+                        *this = MaybeDone::Done(out);
+                        true
+                    }
+                    Poll::Pending => false,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    fn take_output(&mut self) -> F::Output {
+        match core::mem::replace(self, MaybeDone::Gone) {
+            MaybeDone::Done(out) => out,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Wait for all the futures, returns a tuple of their outputs
+///
+/// Must be called in an async context.
+///
+/// # Example
+/// ```
+/// let (a, b, c) = rtt_rs::join!(fut_a, fut_b, fut_c);
+/// ```
+#[macro_export]
+macro_rules! join {
+    ($f1:expr, $f2:expr $(,)?) => {
+        $crate::embassy_async::futures::join($f1, $f2).await
+    };
+    ($f1:expr, $f2:expr, $f3:expr $(,)?) => {
+        $crate::embassy_async::futures::join3($f1, $f2, $f3).await
+    };
+}
+
+/// Wait for the first future to complete and run its branch,
+/// the other futures are dropped
+///
+/// Must be called in an async context, supports 2 or 3 branches.
+///
+/// # Example
+/// ```
+/// let v = rtt_rs::select! {
+///     v = fut_a => v,
+///     _ = timer => 0,
+/// };
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($p1:pat = $f1:expr => $e1:expr, $p2:pat = $f2:expr => $e2:expr $(,)?) => {
+        match $crate::embassy_async::futures::select($f1, $f2).await {
+            $crate::embassy_async::futures::Either::First($p1) => $e1,
+            $crate::embassy_async::futures::Either::Second($p2) => $e2,
+        }
+    };
+    ($p1:pat = $f1:expr => $e1:expr, $p2:pat = $f2:expr => $e2:expr,
+     $p3:pat = $f3:expr => $e3:expr $(,)?) => {
+        match $crate::embassy_async::futures::select3($f1, $f2, $f3).await {
+            $crate::embassy_async::futures::Either3::First($p1) => $e1,
+            $crate::embassy_async::futures::Either3::Second($p2) => $e2,
+            $crate::embassy_async::futures::Either3::Third($p3) => $e3,
+        }
+    };
+}
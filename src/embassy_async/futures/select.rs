@@ -0,0 +1,103 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Output of `select`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+/// Output of `select3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Either3<A, B, C> {
+    First(A),
+    Second(B),
+    Third(C),
+}
+
+/// Future returned by `select`
+pub struct Select<A, B> {
+    a: A,
+    b: B,
+}
+
+/// Wait for the first of two futures to complete
+///
+/// The other future is dropped with the `Select`.
+/// When both are ready at the same poll, the first one wins.
+pub fn select<A: Future, B: Future>(a: A, b: B) -> Select<A, B> {
+    Select { a, b }
+}
+
+impl<A: Future, B: Future> Future for Select<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        /* the futures are never moved out of the pinned `Select` */
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+            return Poll::Ready(Either::First(v));
+        }
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+            return Poll::Ready(Either::Second(v));
+        }
+        Poll::Pending
+    }
+}
+
+/// Future returned by `select3`
+pub struct Select3<A, B, C> {
+    a: A,
+    b: B,
+    c: C,
+}
+
+/// Wait for the first of three futures to complete
+pub fn select3<A: Future, B: Future, C: Future>(a: A, b: B, c: C) -> Select3<A, B, C> {
+    Select3 { a, b, c }
+}
+
+impl<A: Future, B: Future, C: Future> Future for Select3<A, B, C> {
+    type Output = Either3<A::Output, B::Output, C::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+            return Poll::Ready(Either3::First(v));
+        }
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+            return Poll::Ready(Either3::Second(v));
+        }
+        if let Poll::Ready(v) = unsafe { Pin::new_unchecked(&mut this.c) }.poll(cx) {
+            return Poll::Ready(Either3::Third(v));
+        }
+        Poll::Pending
+    }
+}
+
+/// Future returned by `select_array`
+pub struct SelectArray<F, const N: usize> {
+    futures: [F; N],
+}
+
+/// Wait for the first future of an array to complete,
+/// returns its output and its index
+pub fn select_array<F: Future, const N: usize>(futures: [F; N]) -> SelectArray<F, N> {
+    SelectArray { futures }
+}
+
+impl<F: Future, const N: usize> Future for SelectArray<F, N> {
+    type Output = (F::Output, usize);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        for (i, f) in this.futures.iter_mut().enumerate() {
+            if let Poll::Ready(v) = unsafe { Pin::new_unchecked(f) }.poll(cx) {
+                return Poll::Ready((v, i));
+            }
+        }
+        Poll::Pending
+    }
+}
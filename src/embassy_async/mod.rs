@@ -1 +1,8 @@
+pub mod blocking;
+pub mod bridge;
 pub mod executor;
+pub mod futures;
+pub mod sync;
+
+#[cfg(feature = "time")]
+pub mod time;
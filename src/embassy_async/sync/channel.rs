@@ -0,0 +1,256 @@
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, Waker};
+
+use super::waker::{wake_all, MultiWakerRegistration, ThreadParker};
+use crate::raw_api::no_irq;
+
+/// Max number of tasks waiting on each side before they are woken to re-register
+const WAITERS: usize = 4;
+
+/// Bounded multi-producer multi-consumer channel holding up to `N` values
+///
+/// Tasks `send` / `receive` asynchronously,
+/// rt-thread threads and interrupts use `try_send` / `blocking_send`.
+pub struct Channel<T, const N: usize> {
+    state: UnsafeCell<State<T, N>>,
+}
+
+struct State<T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    /* index of the oldest value */
+    head: usize,
+    len: usize,
+    senders: MultiWakerRegistration<WAITERS>,
+    receivers: MultiWakerRegistration<WAITERS>,
+}
+
+unsafe impl<T: Send, const N: usize> Send for Channel<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for Channel<T, N> {}
+
+/// Error returned by `Channel::try_send`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TrySendError<T> {
+    /// The channel is full, the value is given back
+    Full(T),
+}
+
+/// Error returned by `Channel::try_receive`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TryReceiveError {
+    /// The channel is empty
+    Empty,
+}
+
+impl<T, const N: usize> State<T, N> {
+    const UNINIT: MaybeUninit<T> = MaybeUninit::uninit();
+
+    fn push(&mut self, val: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(val);
+        }
+        let idx = (self.head + self.len) % N;
+        self.buf[idx] = MaybeUninit::new(val);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let val = unsafe { ptr::read(self.buf[self.head].as_ptr()) };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(val)
+    }
+}
+
+impl<T, const N: usize> Channel<T, N> {
+    /// Create an empty channel
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                buf: [State::<T, N>::UNINIT; N],
+                head: 0,
+                len: 0,
+                senders: MultiWakerRegistration::new(),
+                receivers: MultiWakerRegistration::new(),
+            }),
+        }
+    }
+
+    /// Send a value without waiting, fails if the channel is full
+    ///
+    /// Can be called from threads and interrupt service functions
+    pub fn try_send(&self, val: T) -> Result<(), TrySendError<T>> {
+        let wakers = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            state
+                .push(val)
+                .map(|_| state.receivers.take_all())
+                .map_err(TrySendError::Full)
+        })?;
+
+        wake_all(wakers);
+        Ok(())
+    }
+
+    /// Receive a value without waiting, fails if the channel is empty
+    pub fn try_receive(&self) -> Result<T, TryReceiveError> {
+        let ret = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            state.pop().map(|v| (v, state.senders.take_all()))
+        });
+
+        match ret {
+            Some((v, wakers)) => {
+                wake_all(wakers);
+                Ok(v)
+            }
+            None => Err(TryReceiveError::Empty),
+        }
+    }
+
+    /// Send a value, waits until there is room in the channel
+    pub fn send(&self, val: T) -> SendFuture<'_, T, N> {
+        SendFuture {
+            channel: self,
+            val: Some(val),
+        }
+    }
+
+    /// Receive a value, waits until the channel is not empty
+    pub fn receive(&self) -> ReceiveFuture<'_, T, N> {
+        ReceiveFuture { channel: self }
+    }
+
+    /// Send a value from a rt-thread thread, waits until there is room in the channel
+    ///
+    /// # Note
+    /// While the channel is full the thread waits on a semaphore released by the receivers,
+    /// do not call it from interrupt service functions or async tasks
+    ///
+    /// # Panics
+    /// Panics if the semaphore to wait on can not be created
+    pub fn blocking_send(&self, val: T) {
+        let mut val = match self.try_send(val) {
+            Ok(()) => return,
+            Err(TrySendError::Full(v)) => v,
+        };
+
+        let parker = ThreadParker::new();
+        let waker = Waker::from(parker.clone());
+        loop {
+            /* registered in the same critical section as the retry, no receive is missed */
+            let (ret, wakers) = no_irq(|| unsafe {
+                let state = &mut *self.state.get();
+                match state.push(val) {
+                    Ok(()) => (Ok(()), Some(state.receivers.take_all())),
+                    Err(v) => (Err(v), state.senders.register(&waker)),
+                }
+            });
+
+            if let Some(wakers) = wakers {
+                wake_all(wakers);
+            }
+            match ret {
+                Ok(()) => return,
+                Err(v) => val = v,
+            }
+            parker.park();
+        }
+    }
+
+    /// Number of values in the channel
+    pub fn len(&self) -> usize {
+        no_irq(|| unsafe { (*self.state.get()).len })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T, const N: usize> Drop for Channel<T, N> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+        while let Some(v) = state.pop() {
+            drop(v);
+        }
+    }
+}
+
+/// Future returned by `Channel::send`
+pub struct SendFuture<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+    val: Option<T>,
+}
+
+impl<'a, T, const N: usize> Unpin for SendFuture<'a, T, N> {}
+
+impl<'a, T, const N: usize> Future for SendFuture<'a, T, N> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let val = match this.val.take() {
+            Some(v) => v,
+            None => return Poll::Ready(()),
+        };
+
+        let (ret, wakers) = no_irq(|| unsafe {
+            let state = &mut *this.channel.state.get();
+            match state.push(val) {
+                Ok(()) => (Ok(()), Some(state.receivers.take_all())),
+                Err(v) => (Err(v), state.senders.register(cx.waker())),
+            }
+        });
+
+        if let Some(wakers) = wakers {
+            wake_all(wakers);
+        }
+        match ret {
+            Ok(()) => Poll::Ready(()),
+            Err(v) => {
+                this.val = Some(v);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Future returned by `Channel::receive`
+pub struct ReceiveFuture<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<'a, T, const N: usize> Future for ReceiveFuture<'a, T, N> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let (ret, wakers) = no_irq(|| unsafe {
+            let state = &mut *self.channel.state.get();
+            match state.pop() {
+                Some(v) => (Poll::Ready(v), Some(state.senders.take_all())),
+                None => (Poll::Pending, state.receivers.register(cx.waker())),
+            }
+        });
+
+        if let Some(wakers) = wakers {
+            wake_all(wakers);
+        }
+        ret
+    }
+}
@@ -0,0 +1,39 @@
+//! Synchronization primitives for async tasks
+//!
+//! All of them are woken through the executor's `Waker`s and protect their state
+//! with `no_irq` critical sections, so the non-async methods (`Signal::signal`,
+//! `Channel::try_send`, `Notify::notify` ...) can also be called from rt-thread
+//! threads and interrupt service functions.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::embassy_async::sync::{Channel, Signal};
+//!
+//! static CHANNEL: Channel<u32, 4> = Channel::new();
+//! static DONE: Signal<()> = Signal::new();
+//!
+//! #[rtt_rs::task]
+//! async fn consumer() {
+//!     loop {
+//!         let v = CHANNEL.receive().await;
+//!         if v == 0 {
+//!             DONE.signal(());
+//!             break;
+//!         }
+//!     }
+//! }
+//!
+//! /* in any rt-thread thread */
+//! CHANNEL.blocking_send(0);
+//! ```
+
+mod channel;
+mod mutex;
+mod notify;
+mod signal;
+pub(crate) mod waker;
+
+pub use channel::{Channel, ReceiveFuture, SendFuture, TryReceiveError, TrySendError};
+pub use mutex::{LockFuture, Mutex, MutexGuard};
+pub use notify::{Notified, Notify};
+pub use signal::{Signal, WaitFuture};
@@ -0,0 +1,129 @@
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::waker::{wake_all, MultiWakerRegistration};
+use crate::raw_api::no_irq;
+
+/// Max number of tasks waiting before they are woken to re-register
+const WAITERS: usize = 4;
+
+/// Mutex for async tasks
+///
+/// Unlike `crate::mutex::Mutex`, a task waiting for the lock
+/// gives the executor back instead of blocking the thread.
+pub struct Mutex<T: ?Sized> {
+    state: UnsafeCell<State>,
+    data: UnsafeCell<T>,
+}
+
+struct State {
+    locked: bool,
+    waiters: MultiWakerRegistration<WAITERS>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(val: T) -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                locked: false,
+                waiters: MultiWakerRegistration::new(),
+            }),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Lock the mutex, waits until it is unlocked
+    pub fn lock(&self) -> LockFuture<'_, T> {
+        LockFuture { mutex: self }
+    }
+
+    /// Lock the mutex without waiting
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let ok = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            !core::mem::replace(&mut state.locked, true)
+        });
+        if ok {
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+/// Future returned by `Mutex::lock`
+pub struct LockFuture<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T: ?Sized> Future for LockFuture<'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mutex = self.mutex;
+        let (ok, wakers) = no_irq(|| unsafe {
+            let state = &mut *mutex.state.get();
+            if state.locked {
+                (false, state.waiters.register(cx.waker()))
+            } else {
+                state.locked = true;
+                (true, None)
+            }
+        });
+
+        if let Some(wakers) = wakers {
+            wake_all(wakers);
+        }
+        if ok {
+            Poll::Ready(MutexGuard { mutex })
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Unlocks the mutex when dropped
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        let wakers = no_irq(|| unsafe {
+            let state = &mut *self.mutex.state.get();
+            state.locked = false;
+            state.waiters.take_all()
+        });
+        wake_all(wakers);
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
@@ -0,0 +1,108 @@
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::waker::{wake_all, MultiWakerRegistration};
+use crate::raw_api::no_irq;
+
+/// Max number of tasks waiting before they are woken to re-register
+const WAITERS: usize = 4;
+
+/// Notify waiting tasks of an event, without a value
+///
+/// `notify` stores a single permit taken by one waiting task,
+/// a permit stored before any task waits is not lost.
+/// `notify_all` wakes every task waiting at the time of the call.
+pub struct Notify {
+    state: UnsafeCell<State>,
+}
+
+struct State {
+    permit: bool,
+    /* incremented by every `notify_all` */
+    generation: u32,
+    waiters: MultiWakerRegistration<WAITERS>,
+}
+
+unsafe impl Send for Notify {}
+unsafe impl Sync for Notify {}
+
+impl Notify {
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                permit: false,
+                generation: 0,
+                waiters: MultiWakerRegistration::new(),
+            }),
+        }
+    }
+
+    /// Wake one waiting task, or the next task to wait if none is waiting
+    pub fn notify(&self) {
+        let wakers = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            state.permit = true;
+            state.waiters.take_all()
+        });
+        /* all waiters race for the permit, the losers wait again */
+        wake_all(wakers);
+    }
+
+    /// Wake all the waiting tasks, does not store a permit
+    pub fn notify_all(&self) {
+        let wakers = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            state.generation = state.generation.wrapping_add(1);
+            state.waiters.take_all()
+        });
+        wake_all(wakers);
+    }
+
+    /// Wait for a notification
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: None,
+        }
+    }
+}
+
+/// Future returned by `Notify::notified`
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    /* generation seen by the first poll */
+    generation: Option<u32>,
+}
+
+impl<'a> Unpin for Notified<'a> {}
+
+impl<'a> Future for Notified<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let (ready, wakers) = no_irq(|| unsafe {
+            let state = &mut *this.notify.state.get();
+            let seen = *this.generation.get_or_insert(state.generation);
+            if seen != state.generation {
+                (true, None)
+            } else if state.permit {
+                state.permit = false;
+                (true, None)
+            } else {
+                (false, state.waiters.register(cx.waker()))
+            }
+        });
+
+        if let Some(wakers) = wakers {
+            wake_all(wakers);
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::waker::WakerRegistration;
+use crate::raw_api::no_irq;
+
+/// Send a value to a single waiting task
+///
+/// A new value overwrites the previous one if it has not been taken yet,
+/// so only the latest value is seen by the waiting task.
+pub struct Signal<T> {
+    state: UnsafeCell<State<T>>,
+}
+
+struct State<T> {
+    value: Option<T>,
+    waker: WakerRegistration,
+}
+
+unsafe impl<T: Send> Send for Signal<T> {}
+unsafe impl<T: Send> Sync for Signal<T> {}
+
+impl<T> Signal<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                value: None,
+                waker: WakerRegistration::new(),
+            }),
+        }
+    }
+
+    /// Set the value and wake the waiting task
+    pub fn signal(&self, val: T) {
+        let (old, waker) = no_irq(|| unsafe {
+            let state = &mut *self.state.get();
+            (state.value.replace(val), state.waker.take())
+        });
+
+        drop(old);
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+
+    /// Clear the value without waking anyone
+    pub fn reset(&self) {
+        let old = no_irq(|| unsafe { (*self.state.get()).value.take() });
+        drop(old);
+    }
+
+    /// Check if a value is set
+    pub fn signaled(&self) -> bool {
+        no_irq(|| unsafe { (*self.state.get()).value.is_some() })
+    }
+
+    /// Take the value if it is set
+    pub fn try_take(&self) -> Option<T> {
+        no_irq(|| unsafe { (*self.state.get()).value.take() })
+    }
+
+    /// Wait for a value and take it
+    ///
+    /// # Note
+    /// Only one task can wait at the same time,
+    /// a second waiting task replaces the first one.
+    pub fn wait(&self) -> WaitFuture<'_, T> {
+        WaitFuture { signal: self }
+    }
+}
+
+/// Future returned by `Signal::wait`
+pub struct WaitFuture<'a, T> {
+    signal: &'a Signal<T>,
+}
+
+impl<'a, T> Future for WaitFuture<'a, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let (ret, old) = no_irq(|| unsafe {
+            let state = &mut *self.signal.state.get();
+            match state.value.take() {
+                Some(v) => (Poll::Ready(v), None),
+                None => (Poll::Pending, state.waker.register(cx.waker())),
+            }
+        });
+
+        /* the replaced task must not wait forever, wake it with interrupts enabled */
+        if let Some(w) = old {
+            w.wake();
+        }
+        ret
+    }
+}
@@ -0,0 +1,108 @@
+use crate::alloc::task::Wake;
+use crate::semaphore::Semaphore;
+use crate::Arc;
+use core::mem;
+use core::task::Waker;
+
+/// Storage for a single waiting task
+///
+/// Not synchronized, the owner must access it in a critical section.
+pub(crate) struct WakerRegistration {
+    waker: Option<Waker>,
+}
+
+impl WakerRegistration {
+    pub(crate) const fn new() -> Self {
+        Self { waker: None }
+    }
+
+    /// Register `w`, returns the replaced waker of another task.
+    ///
+    /// Wake the returned waker outside the critical section,
+    /// the task then polls again and registers itself again.
+    pub(crate) fn register(&mut self, w: &Waker) -> Option<Waker> {
+        match &self.waker {
+            Some(old) if old.will_wake(w) => None,
+            _ => self.waker.replace(w.clone()),
+        }
+    }
+
+    /// Take the waker out, wake it outside the critical section
+    pub(crate) fn take(&mut self) -> Option<Waker> {
+        self.waker.take()
+    }
+}
+
+/// Storage for up to `N` waiting tasks
+///
+/// Not synchronized, the owner must access it in a critical section.
+pub(crate) struct MultiWakerRegistration<const N: usize> {
+    wakers: [Option<Waker>; N],
+}
+
+impl<const N: usize> MultiWakerRegistration<N> {
+    const NONE: Option<Waker> = None;
+
+    pub(crate) const fn new() -> Self {
+        Self {
+            wakers: [Self::NONE; N],
+        }
+    }
+
+    /// Register `w`.
+    ///
+    /// When all the slots are in use they are returned to be woken,
+    /// the tasks then poll again and register themselves again.
+    pub(crate) fn register(&mut self, w: &Waker) -> Option<[Option<Waker>; N]> {
+        if self.wakers.iter().flatten().any(|old| old.will_wake(w)) {
+            return None;
+        }
+
+        let mut overflow = None;
+        if self.wakers.iter().all(|s| s.is_some()) {
+            overflow = Some(self.take_all());
+        }
+
+        for slot in self.wakers.iter_mut() {
+            if slot.is_none() {
+                *slot = Some(w.clone());
+                break;
+            }
+        }
+        overflow
+    }
+
+    /// Take all the wakers out, wake them outside the critical section
+    pub(crate) fn take_all(&mut self) -> [Option<Waker>; N] {
+        mem::replace(&mut self.wakers, [Self::NONE; N])
+    }
+}
+
+/// Wake all the wakers taken out by `MultiWakerRegistration`
+pub(crate) fn wake_all<const N: usize>(wakers: [Option<Waker>; N]) {
+    for w in wakers.iter().flatten() {
+        w.wake_by_ref();
+    }
+}
+
+/// Lets a rt-thread thread wait like a task: its waker releases a semaphore
+pub(crate) struct ThreadParker(Semaphore);
+
+impl ThreadParker {
+    /// # Panics
+    /// Panics if the semaphore can not be created
+    pub(crate) fn new() -> Arc<ThreadParker> {
+        Arc::new(ThreadParker(Semaphore::new().unwrap()))
+    }
+
+    /// Block until the waker was woken since the last `park`
+    pub(crate) fn park(&self) {
+        let _ = self.0.take_wait_forever();
+    }
+}
+
+impl Wake for ThreadParker {
+    fn wake(self: Arc<Self>) {
+        self.0.release();
+    }
+}
@@ -0,0 +1,129 @@
+//! Time driver backed by `rt_tick_get` and an rt-thread timer
+
+use crate::base::{CString, CVoid};
+use crate::raw_api::no_irq;
+use crate::Box;
+use core::cell::Cell;
+
+extern "C" {
+    fn rt_tick_get() -> u32;
+    fn rt_tick_from_millisecond(ms: i32) -> u32;
+    fn rt_timer_create(
+        name: *const u8,
+        timeout: extern "C" fn(parameter: *mut CVoid),
+        parameter: *mut CVoid,
+        time: u32,
+        flag: u8,
+    ) -> *const CVoid;
+    fn rt_timer_start(timer: *const CVoid) -> isize;
+    fn rt_timer_stop(timer: *const CVoid) -> isize;
+    fn rt_timer_control(timer: *const CVoid, cmd: i32, arg: *mut CVoid) -> isize;
+    fn rt_timer_delete(timer: *const CVoid) -> isize;
+}
+
+const RT_TIMER_FLAG_ONE_SHOT: u8 = 0x0;
+const RT_TIMER_CTRL_SET_TIME: i32 = 0x0;
+/* the kernel does not accept a timeout over RT_TICK_MAX / 2 */
+const MAX_ALARM_TICKS: u64 = (u32::MAX / 2 - 1) as u64;
+
+struct TickCounter {
+    last: Cell<u32>,
+    high: Cell<u32>,
+}
+
+unsafe impl Sync for TickCounter {}
+
+static TICKS: TickCounter = TickCounter {
+    last: Cell::new(0),
+    high: Cell::new(0),
+};
+
+/// Current tick extended to 64 bits
+///
+/// The 32 bits system tick wraps, the wrap is detected
+/// as long as `now` is called at least once per wrap period.
+pub(crate) fn now() -> u64 {
+    no_irq(|| {
+        let tick = unsafe { rt_tick_get() };
+        if tick < TICKS.last.get() {
+            TICKS.high.set(TICKS.high.get().wrapping_add(1));
+        }
+        TICKS.last.set(tick);
+        (TICKS.high.get() as u64) << 32 | tick as u64
+    })
+}
+
+pub(crate) fn tick_hz() -> u64 {
+    unsafe { rt_tick_from_millisecond(1000) as u64 }
+}
+
+struct AlarmCallback {
+    func: fn(*mut ()),
+    ctx: *mut (),
+}
+
+/// A one shot timer calling `func(ctx)` when it expires
+pub(crate) struct Alarm {
+    timer: *const CVoid,
+    callback: Box<AlarmCallback>,
+}
+
+impl Alarm {
+    /// # Panics
+    /// Panics if the timer can not be created
+    pub(crate) fn new(func: fn(*mut ()), ctx: *mut ()) -> Alarm {
+        extern "C" fn on_timeout(parameter: *mut CVoid) {
+            let cb = unsafe { &*(parameter as *const AlarmCallback) };
+            (cb.func)(cb.ctx);
+        }
+
+        let callback = Box::new(AlarmCallback { func, ctx });
+        let name = CString::new("alarm");
+        let timer = unsafe {
+            rt_timer_create(
+                name.str.as_ptr(),
+                on_timeout,
+                &*callback as *const _ as *mut CVoid,
+                1,
+                RT_TIMER_FLAG_ONE_SHOT,
+            )
+        };
+        if timer == 0 as *const CVoid {
+            panic!("Can not create the executor alarm.");
+        }
+
+        Alarm { timer, callback }
+    }
+
+    /// Call the callback at tick `at`, `u64::MAX` cancels the alarm
+    ///
+    /// An alarm in the past expires on the next tick,
+    /// the callback is never called synchronously.
+    pub(crate) fn set(&self, at: u64) {
+        unsafe {
+            rt_timer_stop(self.timer);
+        }
+        if at == u64::MAX {
+            return;
+        }
+
+        let mut ticks = at.saturating_sub(now()).max(1).min(MAX_ALARM_TICKS) as u32;
+        unsafe {
+            rt_timer_control(
+                self.timer,
+                RT_TIMER_CTRL_SET_TIME,
+                &mut ticks as *mut u32 as *mut CVoid,
+            );
+            rt_timer_start(self.timer);
+        }
+    }
+}
+
+impl Drop for Alarm {
+    fn drop(&mut self) {
+        unsafe {
+            rt_timer_stop(self.timer);
+            rt_timer_delete(self.timer);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use super::driver::tick_hz;
+
+/// Represents the difference between two `Instant`s, in system ticks
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration {
+    pub(crate) ticks: u64,
+}
+
+impl Duration {
+    pub const fn from_ticks(ticks: u64) -> Duration {
+        Duration { ticks }
+    }
+
+    pub fn from_secs(secs: u64) -> Duration {
+        Duration {
+            ticks: secs * tick_hz(),
+        }
+    }
+
+    /// The tick count is rounded up, a short delay never becomes 0
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration {
+            ticks: (millis * tick_hz() + 999) / 1000,
+        }
+    }
+
+    /// The tick count is rounded up, a short delay never becomes 0
+    pub fn from_micros(micros: u64) -> Duration {
+        Duration {
+            ticks: (micros * tick_hz() + 999_999) / 1_000_000,
+        }
+    }
+
+    pub const fn as_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.ticks / tick_hz()
+    }
+
+    pub fn as_millis(&self) -> u64 {
+        self.ticks * 1000 / tick_hz()
+    }
+
+    pub fn as_micros(&self) -> u64 {
+        self.ticks * 1_000_000 / tick_hz()
+    }
+
+    pub fn checked_add(self, rhs: Duration) -> Option<Duration> {
+        self.ticks.checked_add(rhs.ticks).map(Duration::from_ticks)
+    }
+
+    pub fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        self.ticks.checked_sub(rhs.ticks).map(Duration::from_ticks)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        self.checked_add(rhs)
+            .expect("overflow when adding durations")
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Duration {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting durations")
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl From<core::time::Duration> for Duration {
+    fn from(d: core::time::Duration) -> Duration {
+        Duration::from_micros(d.as_micros() as u64)
+    }
+}
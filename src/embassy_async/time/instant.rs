@@ -0,0 +1,87 @@
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use super::{driver, Duration};
+
+/// An instant in time, in system ticks since boot
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    ticks: u64,
+}
+
+impl Instant {
+    pub const MIN: Instant = Instant { ticks: u64::MIN };
+    pub const MAX: Instant = Instant { ticks: u64::MAX };
+
+    pub fn now() -> Instant {
+        Instant {
+            ticks: driver::now(),
+        }
+    }
+
+    pub const fn from_ticks(ticks: u64) -> Instant {
+        Instant { ticks }
+    }
+
+    pub const fn as_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Time passed since `earlier`, 0 if `earlier` is later than `self`
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_ticks(self.ticks.saturating_sub(earlier.ticks))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.ticks
+            .checked_add(duration.ticks)
+            .map(Instant::from_ticks)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.ticks
+            .checked_sub(duration.ticks)
+            .map(Instant::from_ticks)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        self.checked_add(rhs)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
@@ -0,0 +1,40 @@
+//! Timekeeping for async tasks, only available with feature `time`
+//!
+//! Time is counted in system ticks by `rt_tick_get`,
+//! the executor uses an rt-thread timer to wake up when a task's timer expires.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::embassy_async::time::{with_timeout, Duration, Ticker, Timer};
+//!
+//! async fn blink() {
+//!     Timer::after(Duration::from_millis(100)).await;
+//!
+//!     let mut ticker = Ticker::every(Duration::from_secs(1));
+//!     loop {
+//!         ticker.next().await;
+//!     }
+//! }
+//!
+//! async fn wait_blink() {
+//!     if let Err(_) = with_timeout(Duration::from_secs(5), blink()).await {
+//!         print!("timeout");
+//!     }
+//! }
+//! ```
+
+pub(crate) mod driver;
+mod duration;
+mod instant;
+mod timeout;
+mod timer;
+
+pub use duration::Duration;
+pub use instant::Instant;
+pub use timeout::{with_timeout, TimeoutError, WithTimeout};
+pub use timer::{Ticker, Timer};
+
+/// Number of ticks per second
+pub fn tick_hz() -> u64 {
+    driver::tick_hz()
+}
@@ -0,0 +1,44 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::{Duration, Timer};
+
+/// Error returned by `with_timeout` when the timeout expires first
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeoutError;
+
+/// Run `fut` until it completes or `timeout` expires
+///
+/// `fut` is dropped when the timeout expires.
+pub fn with_timeout<F: Future>(timeout: Duration, fut: F) -> WithTimeout<F> {
+    WithTimeout {
+        fut,
+        timer: Timer::after(timeout),
+    }
+}
+
+/// Future returned by `with_timeout`
+pub struct WithTimeout<F> {
+    fut: F,
+    timer: Timer,
+}
+
+impl<F: Future> Future for WithTimeout<F> {
+    type Output = Result<F::Output, TimeoutError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        /* `fut` is never moved out of the pinned `WithTimeout` */
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        if let Poll::Ready(out) = fut.poll(cx) {
+            return Poll::Ready(Ok(out));
+        }
+
+        match Pin::new(&mut this.timer).poll(cx) {
+            Poll::Ready(_) => Poll::Ready(Err(TimeoutError)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
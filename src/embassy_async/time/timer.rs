@@ -0,0 +1,74 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use super::{Duration, Instant};
+use crate::embassy_async::executor::raw;
+
+/// A future that completes at a specified `Instant`
+///
+/// It must be polled by a task of the rtt_rs executor.
+pub struct Timer {
+    expires_at: Instant,
+    yielded_once: bool,
+}
+
+impl Timer {
+    /// Expire at the specified `Instant`
+    pub fn at(expires_at: Instant) -> Timer {
+        Timer {
+            expires_at,
+            yielded_once: false,
+        }
+    }
+
+    /// Expire after the specified `Duration`
+    ///
+    /// The timer is at least `duration` long, a `Duration` of 0
+    /// still yields to the other tasks once.
+    pub fn after(duration: Duration) -> Timer {
+        Timer::at(Instant::now() + duration)
+    }
+}
+
+impl Unpin for Timer {}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded_once && self.expires_at <= Instant::now() {
+            Poll::Ready(())
+        } else {
+            unsafe { raw::register_timer(self.expires_at, cx.waker()) };
+            self.yielded_once = true;
+            Poll::Pending
+        }
+    }
+}
+
+/// Completes periodically without drift
+///
+/// If a tick is missed, the next calls of `next` return at once
+/// until the ticker has caught up.
+pub struct Ticker {
+    expires_at: Instant,
+    duration: Duration,
+}
+
+impl Ticker {
+    /// The first tick is after `duration`
+    pub fn every(duration: Duration) -> Ticker {
+        Ticker {
+            expires_at: Instant::now() + duration,
+            duration,
+        }
+    }
+
+    /// Wait for the next tick
+    pub fn next(&mut self) -> Timer {
+        let at = self.expires_at;
+        self.expires_at += self.duration;
+        Timer::at(at)
+    }
+}
@@ -5,19 +5,37 @@
 //! let f = fs::File::with_options().read(true).append(true).open("/usr/xxx").unwrap();
 //! f.write(Vec::from("abcdefg".as_bytes())).unwrap();
 //! f.sync().unwrap();
+//!
+//! /* or use caller-provided buffers */
+//! let mut buf = [0_u8; 16];
+//! let n = f.read_into(&mut buf).unwrap();
+//! f.write_bytes(&buf[..n]).unwrap();
 //! ```
 use crate::alloc::fmt::Formatter;
 use crate::base::{CString, RTTError};
+use crate::embassy_async::blocking::try_spawn_blocking;
 use crate::fmt;
 use crate::string::String;
 use crate::vec::Vec;
+use crate::Arc;
 
 pub struct File {
-    fd: i32,
+    fd: Arc<Fd>,
     flag: i32,
     path: String,
 }
 
+/* closed when the file and the worker jobs sharing it are dropped */
+struct Fd(i32);
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe {
+            rttbase::close(self.0);
+        }
+    }
+}
+
 impl fmt::Display for File {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.path)
@@ -35,6 +53,7 @@ mod rttbase {
         pub(crate) fn rename(old: *const u8, new: *const u8) -> i32;
         pub(crate) fn unlink(path_name: *const u8) -> i32;
         pub(crate) fn fsync(fd: i32) -> i32;
+        pub(crate) fn lseek(fd: i32, offset: i32, whence: i32) -> i32;
     }
 }
 
@@ -55,7 +74,7 @@ impl File {
                 Err(RTTError::FileOpenFailed)
             } else {
                 Ok(File {
-                    fd: _fd,
+                    fd: Arc::new(Fd(_fd)),
                     flag: _flag,
                     path: String::from(name),
                 })
@@ -77,7 +96,7 @@ impl File {
 
     pub fn write(&self, buf: Vec<u8>) -> Result<(), RTTError> {
         unsafe {
-            let w_len = rttbase::write(self.fd, buf.as_ptr(), buf.len());
+            let w_len = rttbase::write(self.fd.0, buf.as_ptr(), buf.len());
             return if w_len == buf.len() as i32 {
                 Ok(())
             } else {
@@ -92,7 +111,7 @@ impl File {
 
         let r_len;
         unsafe {
-            r_len = rttbase::read(self.fd, temp.as_mut_ptr(), len);
+            r_len = rttbase::read(self.fd.0, temp.as_mut_ptr(), len);
         }
         return if r_len <= 0 || r_len > len as i32 {
             Err(RTTError::FileReadFailed)
@@ -104,6 +123,53 @@ impl File {
         };
     }
 
+    /// Read into `buf`, returns the number of bytes read, 0 at the end of the file
+    pub fn read_into(&self, buf: &mut [u8]) -> Result<usize, RTTError> {
+        let r_len = unsafe { rttbase::read(self.fd.0, buf.as_mut_ptr(), buf.len()) };
+        if r_len < 0 || r_len as usize > buf.len() {
+            Err(RTTError::FileReadFailed)
+        } else {
+            Ok(r_len as usize)
+        }
+    }
+
+    /// Write `buf`, returns the number of bytes written
+    pub fn write_bytes(&self, buf: &[u8]) -> Result<usize, RTTError> {
+        let w_len = unsafe { rttbase::write(self.fd.0, buf.as_ptr(), buf.len()) };
+        if w_len < 0 || w_len as usize > buf.len() {
+            Err(RTTError::FileWriteFailed)
+        } else {
+            Ok(w_len as usize)
+        }
+    }
+
+    /// `write` on a worker thread
+    ///
+    /// # Note:
+    /// The fd stays open until the job is finished, even if the future is dropped
+    pub async fn write_async(&self, buf: Vec<u8>) -> Result<(), RTTError> {
+        let f = self.share();
+        try_spawn_blocking(move || f.write(buf))?.await
+    }
+
+    /// `read` on a worker thread
+    ///
+    /// # Note:
+    /// The fd stays open until the job is finished, even if the future is dropped
+    pub async fn read_async(&self, len: usize) -> Result<Vec<u8>, RTTError> {
+        let f = self.share();
+        try_spawn_blocking(move || f.read(len))?.await
+    }
+
+    /* a `File` for a worker job, sharing the fd */
+    fn share(&self) -> File {
+        File {
+            fd: self.fd.clone(),
+            flag: self.flag,
+            path: String::new(),
+        }
+    }
+
     pub fn delete(path: &str) -> Result<(), RTTError> {
         unsafe {
             let name: CString = path.into();
@@ -129,7 +195,7 @@ impl File {
 
     pub fn sync(&self) -> Result<(), RTTError> {
         unsafe {
-            if 0 != rttbase::fsync(self.fd) {
+            if 0 != rttbase::fsync(self.fd.0) {
                 Err(RTTError::FileWriteFailed)
             } else {
                 Ok(())
@@ -231,10 +297,112 @@ impl OpenOptions {
     }
 }
 
-impl Drop for File {
-    fn drop(&mut self) {
-        unsafe {
-            rttbase::close(self.fd);
+#[cfg(feature = "embedded-io")]
+mod io {
+    use super::{rttbase, File};
+    use crate::base::RTTError;
+    use core::convert::TryFrom;
+    use embedded_io::{ErrorType, Read, ReadReady, Seek, SeekFrom, Write, WriteReady};
+
+    const SEEK_SET: i32 = 0;
+    const SEEK_CUR: i32 = 1;
+    const SEEK_END: i32 = 2;
+
+    pub(super) fn seek(fd: i32, pos: SeekFrom) -> Result<u64, RTTError> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(o) => (i32::try_from(o).ok(), SEEK_SET),
+            SeekFrom::End(o) => (i32::try_from(o).ok(), SEEK_END),
+            SeekFrom::Current(o) => (i32::try_from(o).ok(), SEEK_CUR),
+        };
+        let offset = offset.ok_or(RTTError::FileSeekFailed)?;
+        let ret = unsafe { rttbase::lseek(fd, offset, whence) };
+        if ret < 0 {
+            Err(RTTError::FileSeekFailed)
+        } else {
+            Ok(ret as u64)
+        }
+    }
+
+    impl ErrorType for File {
+        type Error = RTTError;
+    }
+
+    impl Read for File {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, RTTError> {
+            self.read_into(buf)
+        }
+    }
+
+    impl Write for File {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, RTTError> {
+            self.write_bytes(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), RTTError> {
+            self.sync()
+        }
+    }
+
+    impl Seek for File {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, RTTError> {
+            seek(self.fd.0, pos)
+        }
+    }
+
+    impl ReadReady for File {
+        fn read_ready(&mut self) -> Result<bool, RTTError> {
+            Ok(true)
+        }
+    }
+
+    impl WriteReady for File {
+        fn write_ready(&mut self) -> Result<bool, RTTError> {
+            Ok(true)
+        }
+    }
+}
+
+/// Reads and writes run on the worker threads of `embassy_async::blocking`
+/// with a copy of the buffer, a cancelled call keeps the fd open until it is finished
+#[cfg(feature = "embedded-io-async")]
+mod io_async {
+    use super::File;
+    use crate::base::RTTError;
+    use crate::embassy_async::blocking::try_spawn_blocking;
+    use crate::vec;
+    use embedded_io_async::{Read, Seek, SeekFrom, Write};
+
+    impl Read for File {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, RTTError> {
+            let (f, len) = (self.share(), buf.len());
+            let data = try_spawn_blocking(move || {
+                let mut data = vec![0_u8; len];
+                let r_len = f.read_into(&mut data)?;
+                data.truncate(r_len);
+                Ok::<_, RTTError>(data)
+            })?
+            .await?;
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+    }
+
+    impl Write for File {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, RTTError> {
+            let (f, data) = (self.share(), buf.to_vec());
+            try_spawn_blocking(move || f.write_bytes(&data))?.await
+        }
+
+        async fn flush(&mut self) -> Result<(), RTTError> {
+            let f = self.share();
+            try_spawn_blocking(move || f.sync())?.await
+        }
+    }
+
+    /// `lseek` only moves the file position, it runs on the calling thread
+    impl Seek for File {
+        async fn seek(&mut self, pos: SeekFrom) -> Result<u64, RTTError> {
+            super::io::seek(self.fd.0, pos)
         }
     }
 }
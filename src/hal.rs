@@ -0,0 +1,117 @@
+//! embedded-hal 1.0 support, only available with feature `embedded-hal`
+//!
+//! The device wrappers implement the embedded-hal traits:
+//! - `device::spi::SPI`: `SpiDevice` and `SpiBus`
+//! - `device::i2c::I2CDevice`: `I2c`
+//! - `device::pin::PIN`: `InputPin`, `OutputPin` and `StatefulOutputPin`
+//! - `device::pwm::PWMChannel`: `SetDutyCycle`
+//!
+//! `Delay` implements `DelayNs`.
+//!
+//! With feature `embedded-hal-async` the async traits are implemented too:
+//! `SPI`: `SpiDevice`, `I2CDevice`: `I2c`, `device::pin::AsyncPin`: `Wait`,
+//! and `Delay`: `DelayNs` with the executor timers (needs feature `time`).
+//!
+//! # Example
+//! ```
+//! use embedded_hal::delay::DelayNs;
+//! use rtt_rs::hal::Delay;
+//!
+//! let mut delay = Delay;
+//! delay.delay_us(50);
+//! delay.delay_ms(10);
+//! ```
+
+use crate::base::RTTError;
+#[cfg(all(feature = "embedded-hal-async", feature = "time"))]
+use crate::embassy_async::time::{Duration, Timer};
+use crate::thread::Thread;
+use embedded_hal::delay::DelayNs;
+
+extern "C" {
+    fn rt_hw_us_delay(us: u32);
+}
+
+/// Delay provider
+///
+/// Delays of whole milliseconds sleep the thread with `rt_thread_mdelay`,
+/// the rest is busy waiting with `rt_hw_us_delay` of the BSP.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Delay;
+
+pub(crate) fn delay_ns(ns: u32) {
+    /* round up, a delay must not be shorter than requested */
+    let us = (ns as u64 + 999) / 1000;
+    delay_us(us as u32);
+}
+
+fn delay_us(us: u32) {
+    if us >= 1000 {
+        Thread::mdelay((us / 1000) as i32);
+    }
+    if us % 1000 != 0 {
+        unsafe { rt_hw_us_delay(us % 1000) };
+    }
+}
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        delay_ns(ns);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        delay_us(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        Thread::mdelay(ms as i32);
+    }
+}
+
+impl embedded_hal::digital::Error for RTTError {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+
+impl embedded_hal::spi::Error for RTTError {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        embedded_hal::spi::ErrorKind::Other
+    }
+}
+
+impl embedded_hal::i2c::Error for RTTError {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        match self {
+            /* rt_i2c_transfer stops at the first message not acknowledged */
+            RTTError::DeviceTransFailed
+            | RTTError::DeviceReadFailed
+            | RTTError::DeviceWriteFailed => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl embedded_hal::pwm::Error for RTTError {
+    fn kind(&self) -> embedded_hal::pwm::ErrorKind {
+        embedded_hal::pwm::ErrorKind::Other
+    }
+}
+
+/// Sleeps on the executor timers, the delay is rounded up to whole ticks
+#[cfg(all(feature = "embedded-hal-async", feature = "time"))]
+impl embedded_hal_async::delay::DelayNs for Delay {
+    async fn delay_ns(&mut self, ns: u32) {
+        let us = (ns as u64 + 999) / 1000;
+        Timer::after(Duration::from_micros(us)).await
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        Timer::after(Duration::from_micros(us as u64)).await
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        Timer::after(Duration::from_millis(ms as u64)).await
+    }
+}
@@ -0,0 +1,44 @@
+//! embedded-io support, only available with feature `embedded-io`
+//!
+//! - `device::uart::UART`: `Read`, `ReadReady`, `Write` and `WriteReady`
+//! - `fs::File`: `Read`, `ReadReady`, `Write`, `WriteReady` and `Seek`
+//!
+//! With feature `embedded-io-async` the async traits are implemented too:
+//! `device::async_uart::AsyncUart`: `Read` and `Write`, `fs::File`: `Read`, `Write` and `Seek`.
+//!
+//! # Note
+//! `File::read`, `File::write`, `UART::read_exact` and `UART::write_all` of this crate take precedence over the trait methods,
+//! call the trait methods as `Read::read(&mut f, &mut buf)`.
+//!
+//! # Example
+//! ```
+//! use embedded_io::{Read, Write};
+//! use rtt_rs::device::uart::UART;
+//!
+//! let mut dev = UART::new("uart1").open().unwrap();
+//! let mut buf = [0_u8; 16];
+//! let n = dev.read(&mut buf).unwrap();
+//! dev.write_all(&buf[..n]).unwrap();
+//! ```
+
+use crate::base::RTTError;
+use embedded_io::ErrorKind;
+
+impl embedded_io::Error for RTTError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            RTTError::OutOfMemory => ErrorKind::OutOfMemory,
+            RTTError::MutexTakeTimeout
+            | RTTError::SemaphoreTakeTimeout
+            | RTTError::QueueSendTimeout
+            | RTTError::QueueReceiveTimeout
+            | RTTError::DeviceReadTimeout => ErrorKind::TimedOut,
+            RTTError::DeviceNotFound | RTTError::FileNotExist => ErrorKind::NotFound,
+            RTTError::FileExist => ErrorKind::AlreadyExists,
+            RTTError::FileSeekFailed => ErrorKind::InvalidInput,
+            RTTError::FrameTooLong | RTTError::FrameInvalid => ErrorKind::InvalidData,
+            RTTError::FuncUnDefine => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        }
+    }
+}
@@ -42,6 +42,11 @@ pub mod embassy_async;
 
 pub mod thread;
 
+/// SMP support needs a kernel built with `RT_USING_SMP`
+/// `rtt_rs={ version = "x.x.x", features = ["smp"] }`
+#[cfg(feature = "smp")]
+pub mod smp;
+
 /// Default is using device
 /// if you don't want to use it
 /// `rtt_rs={ version = "x.x.x", default-features = false, features = [] }`
@@ -54,10 +59,21 @@ pub mod fs;
 #[cfg(feature = "net")]
 pub mod net;
 
+/// embedded-hal traits for the devices
+/// `rtt_rs={ version = "x.x.x", features = ["embedded-hal"] }`
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
+
+#[cfg(feature = "embedded-io")]
+pub mod io;
+
 mod prelude;
 
 pub use prelude::v1::*;
 
+/// Attribute macros for async tasks, see `rtt_rs_macros`
+pub use rtt_rs_macros::{main, task};
+
 /// This macro is used to indicate the entry function of the system
 ///
 /// # Example
@@ -2,10 +2,11 @@
 
 use crate::base::*;
 use crate::base::{CVoid, RTTError};
+use crate::raw_api::no_irq;
 use crate::Box;
-use core::cell::UnsafeCell;
+use core::cell::{Cell, UnsafeCell};
 use core::marker::PhantomData;
-use core::mem;
+use core::{mem, ptr};
 
 #[inline]
 pub(crate) fn rttbase_queue_create(len: usize, message_size: usize) -> *const CVoid {
@@ -33,6 +34,15 @@ pub(crate) fn rttbase_queue_receive(
     unsafe { rt_mq_recv(handle, msg, msg_size, tick) }
 }
 
+#[inline]
+pub(crate) fn rttbase_queue_urgent(
+    handle: *const CVoid,
+    msg: *const CVoid,
+    msg_size: usize,
+) -> isize {
+    unsafe { rt_mq_urgent(handle, msg, msg_size) }
+}
+
 #[inline]
 pub(crate) fn rttbase_queue_delete(handle: *const CVoid) {
     unsafe {
@@ -46,10 +56,17 @@ unsafe impl<T> Sync for Queue<T> where T: Send {}
 #[derive(Debug)]
 pub struct Queue<T> {
     queue: *const CVoid,
+    /* messages given back while the queue was full, they are older than the queued ones */
+    stash: Cell<*mut StashNode<T>>,
     /* only for store item type */
     item_type: PhantomData<UnsafeCell<Box<T>>>,
 }
 
+struct StashNode<T> {
+    item: T,
+    next: *mut StashNode<T>,
+}
+
 impl<T> Queue<T> {
     pub fn new(max_size: usize) -> Result<Queue<T>, RTTError> {
         let handle = rttbase_queue_create(max_size, Self::mem_size());
@@ -58,10 +75,16 @@ impl<T> Queue<T> {
         }
         Ok(Queue {
             queue: handle,
+            stash: Cell::new(ptr::null_mut()),
             item_type: PhantomData,
         })
     }
 
+    /// get handle in rtthread system
+    pub(crate) fn raw(&self) -> *const CVoid {
+        self.queue
+    }
+
     #[inline]
     pub const fn mem_size() -> usize {
         mem::size_of::<*mut T>()
@@ -81,6 +104,8 @@ impl<T> Queue<T> {
             max_wait,
         ) != 0
         {
+            /* the item was not sent, free it */
+            drop(unsafe { Box::from_raw(s) });
             Err(RTTError::QueueSendTimeout)
         } else {
             Ok(())
@@ -89,6 +114,9 @@ impl<T> Queue<T> {
     }
 
     pub fn receive(&self, max_wait: i32) -> Result<T, RTTError> {
+        if let Some(item) = self.take_stashed() {
+            return Ok(item);
+        }
         let mut ptr = 0 as *mut T;
         let r = rttbase_queue_receive(
             self.queue,
@@ -106,10 +134,51 @@ impl<T> Queue<T> {
             Err(RTTError::QueueReceiveTimeout)
         };
     }
+
+    /// Put a received message back to the front of the queue
+    ///
+    /// If the queue was filled up meanwhile, the message is kept
+    /// on the Rust side and returned by the next `receive`.
+    pub(crate) fn give_back(&self, item: T) {
+        let s = Box::into_raw(Box::new(item));
+        let r = rttbase_queue_urgent(self.queue, &s as *const _ as *const CVoid, Self::mem_size());
+        if r == 0 {
+            return;
+        }
+
+        /* allocated outside of the critical section */
+        let node = Box::into_raw(Box::new(StashNode {
+            item: unsafe { *Box::from_raw(s) },
+            next: ptr::null_mut(),
+        }));
+        no_irq(|| unsafe {
+            (*node).next = self.stash.get();
+            self.stash.set(node);
+        });
+    }
+
+    fn take_stashed(&self) -> Option<T> {
+        if self.stash.get().is_null() {
+            return None;
+        }
+        let node = no_irq(|| {
+            let node = self.stash.get();
+            if !node.is_null() {
+                self.stash.set(unsafe { (*node).next });
+            }
+            node
+        });
+        if node.is_null() {
+            None
+        } else {
+            Some(unsafe { Box::from_raw(node) }.item)
+        }
+    }
 }
 
 impl<T> Drop for Queue<T> {
     fn drop(&mut self) {
+        while self.take_stashed().is_some() {}
         rttbase_queue_delete(self.queue);
     }
 }
@@ -111,7 +111,14 @@ impl Semaphore {
         }
     }
 
+    /// get handle in rtthread system
+    pub(crate) fn raw(&self) -> *const CVoid {
+        unsafe { *self.0.get() }
+    }
+}
+
+impl Drop for Semaphore {
     fn drop(&mut self) {
-        unsafe { rttbase_semaphore_delete(*self.0.get()) }
+        rttbase_semaphore_delete(*self.0.get_mut())
     }
 }
@@ -0,0 +1,146 @@
+//! SMP support, only available with feature `smp`
+//!
+//! The rt-thread kernel must be built with `RT_USING_SMP`.
+//!
+//! # Note
+//! `SpinLock` reserves `SPINLOCK_WORDS` words for `struct rt_spinlock`,
+//! enough for the debug fields of `RT_DEBUGING_SPINLOCK`.
+//! Check it once at startup in C:
+//! `RT_ASSERT(sizeof(struct rt_spinlock) <= rust_spinlock_size());`
+//!
+//! # Example
+//! ```
+//! use rtt_rs::smp::{self, SpinLock};
+//! use rtt_rs::thread::Thread;
+//!
+//! static COUNTER: SpinLock<u32> = SpinLock::new(0);
+//!
+//! let th = Thread::new().name("th").cpu(1).start(move || {
+//!     *COUNTER.lock() += 1;
+//!     print!("run on cpu {}", smp::current_cpu_id());
+//! });
+//! ```
+
+use crate::base::CVoid;
+use atomic_polyfill::{AtomicU8, Ordering};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+extern "C" {
+    fn rt_hw_cpu_id() -> i32;
+    fn rt_spin_lock_init(lock: *mut CVoid);
+    fn rt_spin_lock_irqsave(lock: *mut CVoid) -> usize;
+    fn rt_spin_unlock_irqrestore(lock: *mut CVoid, level: usize);
+}
+
+/// Get the id of the cpu running the current thread
+pub fn current_cpu_id() -> u32 {
+    unsafe { rt_hw_cpu_id() as u32 }
+}
+
+/// Words reserved for `struct rt_spinlock`
+const SPINLOCK_WORDS: usize = 8;
+
+const UNINIT: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+
+/// Memory of `struct rt_spinlock`, initialized by `rt_spin_lock_init` on the first lock
+#[repr(C, align(8))]
+struct RawSpinLock {
+    lock: UnsafeCell<[usize; SPINLOCK_WORDS]>,
+    state: AtomicU8,
+}
+
+impl RawSpinLock {
+    fn get(&self) -> *mut CVoid {
+        let ptr = self.lock.get() as *mut CVoid;
+        if self.state.load(Ordering::Acquire) != READY {
+            match self.state.compare_exchange(
+                UNINIT,
+                INITIALIZING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    unsafe { rt_spin_lock_init(ptr) };
+                    self.state.store(READY, Ordering::Release);
+                }
+                /* another cpu is initializing the lock */
+                Err(_) => {
+                    while self.state.load(Ordering::Acquire) != READY {
+                        core::hint::spin_loop();
+                    }
+                }
+            }
+        }
+        ptr
+    }
+}
+
+/// Bytes reserved for `struct rt_spinlock` by `SpinLock`
+#[no_mangle]
+pub extern "C" fn rust_spinlock_size() -> usize {
+    SPINLOCK_WORDS * core::mem::size_of::<usize>()
+}
+
+/// Spin lock for short critical sections shared between cpus
+///
+/// Interrupts of the current cpu are disabled while the lock is held,
+/// so it can also be used in interrupt service functions.
+/// Do not block or sleep while holding it.
+pub struct SpinLock<T: ?Sized> {
+    raw: RawSpinLock,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for SpinLock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(t: T) -> Self {
+        SpinLock {
+            raw: RawSpinLock {
+                lock: UnsafeCell::new([0; SPINLOCK_WORDS]),
+                state: AtomicU8::new(UNINIT),
+            },
+            data: UnsafeCell::new(t),
+        }
+    }
+}
+
+impl<T: ?Sized> SpinLock<T> {
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        let level = unsafe { rt_spin_lock_irqsave(self.raw.get()) };
+        SpinLockGuard {
+            __lock: self,
+            __level: level,
+        }
+    }
+}
+
+pub struct SpinLockGuard<'a, T: ?Sized + 'a> {
+    __lock: &'a SpinLock<T>,
+    __level: usize,
+}
+
+impl<'a, T: ?Sized> Deref for SpinLockGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.__lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for SpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.__lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for SpinLockGuard<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            rt_spin_unlock_irqrestore(self.__lock.raw.get(), self.__level);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Thread local storage
+//!
+//! Every thread owns a small key/value table which hangs on the `user_data`
+//! field of its `struct rt_thread`. The table is created on first access
+//! and the values are dropped when a thread spawned by `Thread` returns.
+//!
+//! Only available with feature `thread-local`. The kernel does not export
+//! `user_data`, so the C side must provide:
+//! ```c
+//! rt_ubase_t *rttbase_thread_user_data(rt_thread_t th)
+//! {
+//!     return &th->user_data;
+//! }
+//! ```
+//!
+//! # Example
+//! ```
+//! use core::cell::Cell;
+//! use rtt_rs::thread_local;
+//!
+//! thread_local! {
+//!     static COUNTER: Cell<u32> = Cell::new(0);
+//! }
+//!
+//! COUNTER.with(|c| c.set(c.get() + 1));
+//! ```
+//!
+//! # Note
+//! Threads created from C never run the destructors,
+//! their values are leaked when the thread exits.
+
+use crate::base::*;
+use crate::raw_api::rt_thread_self;
+use crate::vec::Vec;
+use crate::Box;
+
+struct Entry {
+    key: usize,
+    value: *mut u8,
+    dtor: unsafe fn(*mut u8),
+}
+
+struct LocalTable {
+    entries: Vec<Entry>,
+}
+
+#[inline]
+fn current_user_data() -> *mut usize {
+    unsafe { rttbase_thread_user_data(rt_thread_self() as *const CVoid) }
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+    drop(Box::from_raw(value as *mut T));
+}
+
+fn current_table() -> &'static mut LocalTable {
+    unsafe {
+        let slot = current_user_data();
+        if *slot == 0 {
+            let table = Box::new(LocalTable {
+                entries: Vec::new(),
+            });
+            *slot = Box::into_raw(table) as usize;
+        }
+        &mut *(*slot as *mut LocalTable)
+    }
+}
+
+/// Drop all values of the current thread
+///
+/// Destructors may touch other keys and create a new table,
+/// so keep going until the thread has no table any more.
+pub(crate) fn run_dtors() {
+    loop {
+        let table = unsafe {
+            let slot = current_user_data();
+            let table = *slot as *mut LocalTable;
+            *slot = 0;
+            table
+        };
+        if table.is_null() {
+            break;
+        }
+        let table = unsafe { Box::from_raw(table) };
+        for entry in table.entries.iter().rev() {
+            unsafe { (entry.dtor)(entry.value) }
+        }
+    }
+}
+
+/// A thread local storage key which owns its contents
+///
+/// Created by the `thread_local!` macro.
+/// Each thread gets its own copy initialized lazily by `init`.
+pub struct LocalKey<T: 'static> {
+    init: fn() -> T,
+}
+
+unsafe impl<T: 'static> Sync for LocalKey<T> {}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(init: fn() -> T) -> LocalKey<T> {
+        LocalKey { init }
+    }
+
+    /// Get a reference to the value of the current thread
+    ///
+    /// The value is initialized on the first call in each thread
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let key = self as *const _ as usize;
+
+        let found = current_table()
+            .entries
+            .iter()
+            .find(|e| e.key == key)
+            .map(|e| e.value);
+
+        let value = match found {
+            Some(v) => v,
+            None => {
+                /* init may access other keys, so the table is not borrowed here */
+                let v = Box::into_raw(Box::new((self.init)())) as *mut u8;
+                current_table().entries.push(Entry {
+                    key,
+                    value: v,
+                    dtor: drop_value::<T>,
+                });
+                v
+            }
+        };
+
+        f(unsafe { &*(value as *const T) })
+    }
+}
+
+/// Declare thread local storage keys of type `LocalKey`
+///
+/// # Example
+/// ```
+/// use core::cell::RefCell;
+/// use rtt_rs::thread_local;
+/// use rtt_rs::vec::Vec;
+///
+/// thread_local! {
+///     static BUF: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+///     pub static ID: u32 = 7;
+/// }
+///
+/// BUF.with(|b| b.borrow_mut().push(1));
+/// ```
+#[cfg(feature = "thread-local")]
+#[macro_export]
+macro_rules! thread_local {
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => {
+        $crate::thread_local!($(#[$attr])* $vis static $name: $t = $init);
+        $crate::thread_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => {
+        $(#[$attr])*
+        $vis static $name: $crate::thread::LocalKey<$t> = {
+            fn __init() -> $t {
+                $init
+            }
+            $crate::thread::LocalKey::new(__init)
+        };
+    };
+}
@@ -20,6 +20,20 @@ use crate::base::*;
 use alloc::string::String;
 use core::mem;
 
+const RT_THREAD_CTRL_BIND_CPU: i32 = 0x04;
+
+#[cfg(feature = "thread-local")]
+mod local;
+mod periodic;
+mod pool;
+mod scope;
+
+#[cfg(feature = "thread-local")]
+pub use local::LocalKey;
+pub use periodic::{Periodic, PeriodicStats};
+pub use pool::{JobHandle, ThreadPool, ThreadPoolBuilder};
+pub use scope::{scope, Scope, ScopedJoinHandle};
+
 #[inline]
 pub(crate) fn rttbase_thread_mdelay(ms: i32) {
     unsafe {
@@ -38,6 +52,22 @@ pub(crate) fn rttbase_thread_delay(tick: u32) {
     unimplemented!()
 }
 
+#[inline]
+pub(crate) fn rttbase_thread_delay_until(last_wake: &mut u32, period: u32) {
+    #[cfg(not(feature = "rt-smart"))]
+    unsafe {
+        rt_thread_delay_until(last_wake as *mut u32, period);
+    }
+
+    #[cfg(feature = "rt-smart")]
+    unimplemented!()
+}
+
+#[inline]
+pub(crate) fn rttbase_tick_get() -> u32 {
+    unsafe { rt_tick_get() }
+}
+
 #[inline]
 pub(crate) fn rttbase_thread_create(
     name: &str,
@@ -63,6 +93,11 @@ pub(crate) fn rttbase_thread_startup(th: *const CVoid) -> isize {
     unsafe { rt_thread_startup(th) }
 }
 
+#[inline]
+pub(crate) fn rttbase_thread_bind_cpu(th: *const CVoid, cpu: u8) -> isize {
+    unsafe { rt_thread_control(th, RT_THREAD_CTRL_BIND_CPU, cpu as usize as *mut CVoid) }
+}
+
 #[inline]
 pub(crate) fn rttbase_thread_yield() {
     unsafe {
@@ -85,12 +120,38 @@ impl Thread {
         rttbase_thread_mdelay(ms);
     }
 
+    /// Delay until `last_wake + period` ticks
+    ///
+    /// `last_wake` is advanced by `period`, so a loop calling this
+    /// will not drift. If the deadline has already passed,
+    /// the system resets `last_wake` to the current tick without delay.
+    ///
+    /// # Example
+    /// ```
+    /// use rtt_rs::thread::Thread;
+    ///
+    /// let mut last_wake = Thread::tick();
+    /// loop {
+    ///     /* ..... */
+    ///     Thread::delay_until(&mut last_wake, 10);
+    /// }
+    /// ```
+    pub fn delay_until(last_wake: &mut u32, period: u32) {
+        rttbase_thread_delay_until(last_wake, period);
+    }
+
+    /// Get the current system tick
+    pub fn tick() -> u32 {
+        rttbase_tick_get()
+    }
+
     pub fn new() -> ThreadBuilder {
         ThreadBuilder {
             th_name: "uname".into(),
             th_stack_size: 4096,
             th_priority: 10,
             th_ticks: 10,
+            th_cpu: None,
         }
     }
 
@@ -115,11 +176,30 @@ impl Thread {
         rttbase_thread_delete(self.0);
     }
 
+    /// Bind the thread to a cpu
+    ///
+    /// # Note
+    /// Only works when the system is built with `RT_USING_SMP`,
+    /// otherwise the system ignores it.
+    /// Please also read the `Note` of `fn delete_thread`
+    pub fn bind_cpu(&self, cpu: u8) -> Result<(), RTTError> {
+        Self::_bind_cpu(self.0, cpu)
+    }
+
+    fn _bind_cpu(th: *const CVoid, cpu: u8) -> Result<(), RTTError> {
+        return if rttbase_thread_bind_cpu(th, cpu) != 0 {
+            Err(RTTError::ThreadControlFailed)
+        } else {
+            Ok(())
+        };
+    }
+
     unsafe fn spawn_inner(
         name: String,
         stack_size: u32,
         priority: u8,
         ticks: u32,
+        cpu: Option<u8>,
         func: Box<dyn FnOnce()>,
     ) -> Result<Self, RTTError> {
         let func = Box::new(func);
@@ -130,6 +210,8 @@ impl Thread {
                 let run = Box::from_raw(param as *mut Box<dyn FnOnce()>);
                 run();
             }
+            #[cfg(feature = "thread-local")]
+            local::run_dtors();
         }
 
         let th_handle = rttbase_thread_create(
@@ -145,6 +227,13 @@ impl Thread {
             return Err(RTTError::OutOfMemory);
         }
 
+        if let Some(cpu) = cpu {
+            if let Err(e) = Self::_bind_cpu(th_handle, cpu) {
+                rttbase_thread_delete(th_handle);
+                return Err(e);
+            }
+        }
+
         let ret = match Self::_startup(th_handle) {
             Ok(_) => {
                 mem::forget(func);
@@ -170,7 +259,8 @@ impl Thread {
         F: FnOnce() -> () + Send + 'static,
     {
         unsafe {
-            return Self::spawn_inner(name, stack_size, priority, ticks, Box::new(func)).unwrap();
+            return Self::spawn_inner(name, stack_size, priority, ticks, None, Box::new(func))
+                .unwrap();
         }
     }
 }
@@ -182,6 +272,7 @@ pub struct ThreadBuilder {
     th_stack_size: u32,
     th_priority: u8,
     th_ticks: u32,
+    th_cpu: Option<u8>,
 }
 
 impl ThreadBuilder {
@@ -205,17 +296,29 @@ impl ThreadBuilder {
         self
     }
 
+    /// Bind the thread to a cpu before it starts
+    ///
+    /// # Note
+    /// Please read the `Note` of `Thread::bind_cpu`
+    pub fn cpu(&mut self, cpu: u8) -> &mut Self {
+        self.th_cpu = Some(cpu);
+        self
+    }
+
     pub fn start<F>(&self, func: F) -> Result<Thread, RTTError>
     where
         F: FnOnce() -> (),
         F: Send + 'static,
     {
-        Ok(Thread::spawn(
-            self.th_name.clone(),
-            self.th_stack_size,
-            self.th_priority,
-            self.th_ticks,
-            func,
-        ))
+        unsafe {
+            Thread::spawn_inner(
+                self.th_name.clone(),
+                self.th_stack_size,
+                self.th_priority,
+                self.th_ticks,
+                self.th_cpu,
+                Box::new(func),
+            )
+        }
     }
 }
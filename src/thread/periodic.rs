@@ -0,0 +1,104 @@
+//! Run a closure at a fixed rate
+//!
+//! # Example
+//! ```
+//! use rtt_rs::thread::Periodic;
+//!
+//! /* 1 kHz loop with a 1000 Hz tick */
+//! let mut p = Periodic::new(1);
+//! p.run(|stats| {
+//!     /* control ..... */
+//!     stats.cycles < 10000
+//! });
+//! print!("overruns: {}", p.stats().overruns);
+//! ```
+
+use super::{rttbase_thread_delay_until, rttbase_tick_get};
+
+/// Timing statistics of a `Periodic`, all times in ticks
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PeriodicStats {
+    /// Number of finished periods
+    pub cycles: u32,
+    /// Number of periods whose work did not finish before the deadline
+    pub overruns: u32,
+    /// Number of deadlines skipped because of overruns
+    pub missed: u32,
+    /// Wake up lateness of the last period
+    pub last_jitter: u32,
+    /// Max wake up lateness seen
+    pub max_jitter: u32,
+}
+
+pub struct Periodic {
+    period: u32,
+    last_wake: u32,
+    stats: PeriodicStats,
+}
+
+impl Periodic {
+    /// New a periodic timer, the first period starts now
+    ///
+    /// # Panics
+    /// Panics if `period` is 0
+    pub fn new(period: u32) -> Periodic {
+        assert!(period > 0, "period must not be 0");
+        Periodic {
+            period,
+            last_wake: rttbase_tick_get(),
+            stats: PeriodicStats::default(),
+        }
+    }
+
+    /// Restart the period from the current tick
+    pub fn reset(&mut self) {
+        self.last_wake = rttbase_tick_get();
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn stats(&self) -> &PeriodicStats {
+        &self.stats
+    }
+
+    /// Wait for the next period
+    ///
+    /// Returns the number of deadlines missed since the last call,
+    /// 0 means the work of this period finished in time.
+    /// After an overrun the next period starts from now.
+    pub fn wait(&mut self) -> u32 {
+        let elapsed = rttbase_tick_get().wrapping_sub(self.last_wake);
+        self.stats.cycles = self.stats.cycles.wrapping_add(1);
+
+        if elapsed >= self.period {
+            let missed = elapsed / self.period;
+            self.stats.overruns = self.stats.overruns.wrapping_add(1);
+            self.stats.missed = self.stats.missed.wrapping_add(missed);
+            self.stats.last_jitter = 0;
+            self.last_wake = rttbase_tick_get();
+            return missed;
+        }
+
+        let expected = self.last_wake.wrapping_add(self.period);
+        rttbase_thread_delay_until(&mut self.last_wake, self.period);
+
+        let jitter = rttbase_tick_get().wrapping_sub(expected);
+        self.stats.last_jitter = jitter;
+        if jitter > self.stats.max_jitter {
+            self.stats.max_jitter = jitter;
+        }
+        0
+    }
+
+    /// Call `func` once per period until it returns `false`
+    pub fn run<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&PeriodicStats) -> bool,
+    {
+        while func(&self.stats) {
+            self.wait();
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! A fixed-size pool of worker threads
+//!
+//! Jobs are passed to the workers through a bounded `Queue`,
+//! so no kernel thread is created per job.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::thread::ThreadPool;
+//!
+//! let pool = ThreadPool::builder()
+//!     .name("pool")
+//!     .threads(4)
+//!     .stack_size(4096)
+//!     .build()
+//!     .unwrap();
+//!
+//! pool.execute(|| print!("hello pool")).unwrap();
+//!
+//! let h = pool.submit(|| 1 + 1).unwrap();
+//! assert_eq!(h.wait(), 2);
+//!
+//! pool.shutdown();
+//! ```
+
+use super::Thread;
+use crate::alloc::format;
+use crate::base::RTTError;
+use crate::queue::Queue;
+use crate::raw_api::no_irq;
+use crate::semaphore::Semaphore;
+use crate::string::String;
+use crate::{Arc, Box};
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+const RT_WAITING_FOREVER: i32 = -1;
+
+enum Message {
+    Job(Box<dyn FnOnce() + Send>),
+    Shutdown,
+}
+
+pub struct ThreadPool {
+    queue: Arc<Queue<Message>>,
+    exited: Arc<Semaphore>,
+    workers: usize,
+    running: bool,
+}
+
+pub struct ThreadPoolBuilder {
+    name: String,
+    threads: usize,
+    queue_size: usize,
+    stack_size: u32,
+    priority: u8,
+    ticks: u32,
+}
+
+impl ThreadPoolBuilder {
+    /// Worker threads are named `name0`, `name1` ...
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Max number of pending jobs, `execute` blocks when the queue is full
+    pub fn queue_size(&mut self, queue_size: usize) -> &mut Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    pub fn stack_size(&mut self, stack_size: u32) -> &mut Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    pub fn priority(&mut self, priority: u8) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn ticks(&mut self, ticks: u32) -> &mut Self {
+        self.ticks = ticks;
+        self
+    }
+
+    pub fn build(&self) -> Result<ThreadPool, RTTError> {
+        let mut pool = ThreadPool {
+            queue: Arc::new(Queue::new(self.queue_size)?),
+            exited: Arc::new(Semaphore::new()?),
+            workers: 0,
+            running: true,
+        };
+
+        for i in 0..self.threads {
+            let queue = pool.queue.clone();
+            let exited = pool.exited.clone();
+            let ret = Thread::new()
+                .name(&format!("{}{}", self.name, i))
+                .stack_size(self.stack_size)
+                .priority(self.priority)
+                .ticks(self.ticks)
+                .start(move || {
+                    while let Ok(Message::Job(job)) = queue.receive(RT_WAITING_FOREVER) {
+                        job();
+                    }
+                    drop(queue);
+                    exited.release();
+                });
+
+            /* dropping the pool stops the workers already started */
+            ret?;
+            pool.workers += 1;
+        }
+
+        Ok(pool)
+    }
+}
+
+impl ThreadPool {
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder {
+            name: "pool".into(),
+            threads: 2,
+            queue_size: 16,
+            stack_size: 4096,
+            priority: 10,
+            ticks: 10,
+        }
+    }
+
+    /// Number of worker threads
+    pub fn threads(&self) -> usize {
+        self.workers
+    }
+
+    /// Run a job on one of the workers
+    ///
+    /// Blocks while the job queue is full
+    pub fn execute<F>(&self, func: F) -> Result<(), RTTError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.queue
+            .send_wait(Message::Job(Box::new(func)), RT_WAITING_FOREVER)
+    }
+
+    /// Run a job on one of the workers without blocking
+    ///
+    /// Fails with `QueueSendTimeout` if the job queue is full, the job is dropped
+    pub fn try_execute<F>(&self, func: F) -> Result<(), RTTError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.queue.send(Message::Job(Box::new(func)))
+    }
+
+    /// Run a job on one of the workers and get a handle to its result
+    ///
+    /// The handle can be waited on by a thread or awaited by an async task
+    pub fn submit<F, T>(&self, func: F) -> Result<JobHandle<T>, RTTError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let packet = Arc::new(Packet::new()?);
+        let their_packet = packet.clone();
+        self.execute(move || their_packet.complete(func()))?;
+        Ok(JobHandle { packet })
+    }
+
+    /// `submit` without blocking
+    ///
+    /// Fails with `QueueSendTimeout` if the job queue is full
+    pub fn try_submit<F, T>(&self, func: F) -> Result<JobHandle<T>, RTTError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let packet = Arc::new(Packet::new()?);
+        let their_packet = packet.clone();
+        self.try_execute(move || their_packet.complete(func()))?;
+        Ok(JobHandle { packet })
+    }
+
+    /// Finish all queued jobs and stop the workers
+    ///
+    /// Blocks until every worker has exited
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if !self.running {
+            return;
+        }
+        self.running = false;
+
+        for _ in 0..self.workers {
+            let _ = self.queue.send_wait(Message::Shutdown, RT_WAITING_FOREVER);
+        }
+        for _ in 0..self.workers {
+            let _ = self.exited.take_wait_forever();
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Packet<T> {
+    result: UnsafeCell<Option<T>>,
+    waker: UnsafeCell<Option<Waker>>,
+    done: Semaphore,
+}
+
+unsafe impl<T: Send> Send for Packet<T> {}
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+impl<T> Packet<T> {
+    fn new() -> Result<Self, RTTError> {
+        Ok(Packet {
+            result: UnsafeCell::new(None),
+            waker: UnsafeCell::new(None),
+            done: Semaphore::new()?,
+        })
+    }
+
+    fn complete(&self, val: T) {
+        let waker = no_irq(|| unsafe {
+            *self.result.get() = Some(val);
+            (*self.waker.get()).take()
+        });
+        self.done.release();
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+}
+
+/// Handle to the result of a job passed to `ThreadPool::submit`
+pub struct JobHandle<T> {
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Block the current thread until the job is finished
+    pub fn wait(self) -> T {
+        let _ = self.packet.done.take_wait_forever();
+        no_irq(|| unsafe { (*self.packet.result.get()).take().unwrap() })
+    }
+
+    /// Get the result if the job is already finished, otherwise the handle is given back
+    pub fn try_wait(self) -> Result<T, JobHandle<T>> {
+        match no_irq(|| unsafe { (*self.packet.result.get()).take() }) {
+            Some(val) => Ok(val),
+            None => Err(self),
+        }
+    }
+}
+
+impl<T> Future for JobHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let packet = &self.packet;
+        no_irq(|| unsafe {
+            match (*packet.result.get()).take() {
+                Some(val) => Poll::Ready(val),
+                None => {
+                    *packet.waker.get() = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
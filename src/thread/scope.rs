@@ -0,0 +1,177 @@
+//! Scoped threads
+//!
+//! Threads spawned in a scope are joined before `scope` returns,
+//! so they can borrow non-`'static` data from the spawning stack.
+//!
+//! # Example
+//! ```
+//! use rtt_rs::thread;
+//!
+//! let mut data = [1, 2, 3, 4];
+//! let (left, right) = data.split_at_mut(2);
+//!
+//! thread::scope(|s| {
+//!     s.spawn(|| left.iter_mut().for_each(|x| *x *= 2));
+//!     s.spawn(|| right.iter_mut().for_each(|x| *x *= 3));
+//! });
+//!
+//! assert_eq!(data, [2, 4, 9, 12]);
+//! ```
+
+use super::{Thread, ThreadBuilder};
+use crate::base::RTTError;
+use crate::semaphore::Semaphore;
+use crate::{Arc, Box};
+use atomic_polyfill::{AtomicUsize, Ordering};
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem;
+
+/// A scope to spawn scoped threads in
+///
+/// See `scope` for details.
+pub struct Scope<'scope, 'env: 'scope> {
+    /* released once by every finished thread, deleted when `scope` returns */
+    done: Semaphore,
+    spawned: AtomicUsize,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+struct Packet<T> {
+    result: UnsafeCell<Option<T>>,
+    /* deleted with the last reference of the thread or its handle */
+    done: Semaphore,
+}
+
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+/// An owned permission to join on a scoped thread
+pub struct ScopedJoinHandle<'scope, T> {
+    thread: Thread,
+    packet: Arc<Packet<T>>,
+    scope: PhantomData<&'scope ()>,
+}
+
+/// Create a scope for spawning scoped threads
+///
+/// All threads spawned in the scope that are not joined manually
+/// will be joined before this function returns.
+///
+/// # Panics
+/// Panics when the completion semaphore can not be created
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    let scope = Scope {
+        done: Semaphore::new().unwrap(),
+        spawned: AtomicUsize::new(0),
+        scope: PhantomData,
+        env: PhantomData,
+    };
+
+    let ret = f(&scope);
+
+    /* scoped threads may spawn more threads, always reload the count.
+     * `rt_sem_release` is the last access of a thread to the scope,
+     * so the semaphore can be deleted as soon as the last one is taken */
+    let mut finished = 0;
+    while finished < scope.spawned.load(Ordering::Acquire) {
+        let _ = scope.done.take_wait_forever();
+        finished += 1;
+    }
+
+    ret
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawn a scoped thread with the default `ThreadBuilder` settings
+    ///
+    /// # Panics
+    /// Panics when the thread can not be created
+    pub fn spawn<F, T>(&'scope self, f: F) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        Thread::new().spawn_scoped(self, f).unwrap()
+    }
+}
+
+impl ThreadBuilder {
+    /// Spawn a scoped thread with this configuration
+    pub fn spawn_scoped<'scope, 'env, F, T>(
+        &self,
+        scope: &'scope Scope<'scope, 'env>,
+        f: F,
+    ) -> Result<ScopedJoinHandle<'scope, T>, RTTError>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let packet = Arc::new(Packet {
+            result: UnsafeCell::new(None),
+            done: Semaphore::new()?,
+        });
+
+        let their_packet = packet.clone();
+        let scope_ptr = scope as *const Scope as usize;
+        let main = move || {
+            let ret = f();
+            unsafe {
+                *their_packet.result.get() = Some(ret);
+            }
+            their_packet.done.release();
+            drop(their_packet);
+
+            /* this must be the last access to the scope */
+            let scope = unsafe { &*(scope_ptr as *const Scope) };
+            scope.done.release();
+        };
+
+        let main: Box<dyn FnOnce() + Send + 'scope> = Box::new(main);
+        /* the scope joins the thread before 'scope ends */
+        let main: Box<dyn FnOnce() + 'static> = unsafe { mem::transmute(main) };
+
+        scope.spawned.fetch_add(1, Ordering::AcqRel);
+        let thread = unsafe {
+            Thread::spawn_inner(
+                self.th_name.clone(),
+                self.th_stack_size,
+                self.th_priority,
+                self.th_ticks,
+                self.th_cpu,
+                main,
+            )
+        };
+
+        match thread {
+            Ok(thread) => Ok(ScopedJoinHandle {
+                thread,
+                packet,
+                scope: PhantomData,
+            }),
+            Err(e) => {
+                scope.spawned.fetch_sub(1, Ordering::AcqRel);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    /// Get the underlying thread
+    ///
+    /// # Note
+    /// Please read the `Note` of `Thread::delete_thread`
+    pub fn thread(&self) -> &Thread {
+        &self.thread
+    }
+
+    /// Wait for the thread to finish and get its result
+    pub fn join(self) -> T {
+        let _ = self.packet.done.take_wait_forever();
+        unsafe { (*self.packet.result.get()).take().unwrap() }
+    }
+}